@@ -1,4 +1,15 @@
-use clap::{Arg, ArgAction, ArgMatches, Command};
+use std::{env, ffi::OsString};
+
+use clap::{
+    Arg, ArgAction, ArgMatches, Command,
+    builder::{PossibleValue, PossibleValuesParser},
+};
+
+use crate::{
+    pattern::Pattern,
+    quoting::{Quoting, QuotingStyle},
+    term,
+};
 
 fn arg_base(name: &'static str, req: bool, about: &'static str) -> Arg {
     Arg::new(name).required(req).help(about)
@@ -30,6 +41,12 @@ fn arg_bool(name: &'static str, req: bool, about: &'static str, value: bool) ->
     })
 }
 
+fn arg_bool_long(name: &'static str, req: bool, about: &'static str) -> Arg {
+    arg_base(name, req, about)
+        .long(name)
+        .action(ArgAction::SetTrue)
+}
+
 fn arg_str(name: &'static str, req: bool, about: &'static str) -> Arg {
     arg_base(name, req, about)
 }
@@ -37,26 +54,174 @@ fn arg_str(name: &'static str, req: bool, about: &'static str) -> Arg {
 pub fn ls_command() -> Command {
     Command::new("fls")
         .about("Fast list files")
-        .arg(arg_str("path", false, "Path to list"))
+        .version(env!("CARGO_PKG_VERSION"))
+        .arg(arg_str("path", false, "Paths to list").action(ArgAction::Append))
         .arg(arg_flag_t("cols", false, "Number of columns", 'C'))
-        .arg(arg_flag_t(
-            "ignore",
+        .arg(
+            arg_flag_t(
+                "ignore",
+                false,
+                "Do not list entries matching the (comma separated) glob patterns",
+                'I',
+            )
+            .action(ArgAction::Append),
+        )
+        .arg(
+            arg_base(
+                "hide",
+                false,
+                "Do not list entries matching the glob pattern, unless -a is given",
+            )
+            .long("hide")
+            .action(ArgAction::Append),
+        )
+        .arg(arg_bool_long(
+            "gitignore",
             false,
-            "Comma separated ignore list",
-            'I',
+            "Skip entries excluded by .gitignore, .ignore and .fdignore files",
+        ))
+        .arg(arg_bool_long(
+            "git",
+            false,
+            "Show the git status of every entry in long and JSON output",
+        ))
+        .arg(arg_bool_long(
+            "regex",
+            false,
+            "Read the patterns of --ignore and --hide as regular expressions",
         ))
         .arg(arg_bool("all", false, "Show hidden files", false))
         .arg(arg_bool("long", false, "Long format", false))
         .arg(arg_bool("numeric", false, "Numbers in left", false))
+        .arg(
+            arg_bool_long(
+                "numeric-uid-gid",
+                false,
+                "Long format with numeric user and group ids",
+            )
+            .visible_alias("numeric-ids"),
+        )
         .arg(arg_bool_t("humanable", false, "Human readable", 'H', false))
-        .arg(arg_base("sort", false, "Sort by value").long("sort"))
+        .arg(
+            arg_base(
+                "sort",
+                false,
+                "Sort by the (comma separated) keys, later ones break ties",
+            )
+            .long("sort")
+            .value_parser(sort_keys())
+            .value_delimiter(','),
+        )
         .arg(arg_bool("reverse", false, "Reverse order", false))
         .arg(arg_bool_t("name", false, "Sort by name", 'N', false))
         .arg(arg_bool_t("time", false, "Sort by time", 'T', false))
         .arg(arg_bool_t("size", false, "Sort by size", 'S', false))
-        // .arg(arg_bool_t("ext", false, "Sort by extension", 'X', false))
-        .arg(arg_flag_t("recursive", false, "Recursive", 'R'))
+        .arg(arg_bool_t(
+            "allocated",
+            false,
+            "Print the allocated size of each file, in 1024-byte blocks",
+            's',
+            false,
+        ))
+        .arg(arg_bool_t("ext", false, "Sort by extension", 'X', false))
+        .arg(arg_bool_long(
+            "group-directories-first",
+            false,
+            "List directories before files",
+        ))
+        .arg(
+            // short only: `--version` prints the version
+            arg_base(
+                "version-sort",
+                false,
+                "Sort numbers within names by their value",
+            )
+            .short('v')
+            .action(ArgAction::SetTrue),
+        )
+        .arg(arg_flag_t(
+            "recursive",
+            false,
+            "List subdirectories recursively (max or a depth) as GNU style `path:` \
+             sections; --indent gives the former layout",
+            'R',
+        ))
         .arg(arg_bool("one", false, "One line input", false))
+        .arg(arg_bool_long(
+            "indent",
+            false,
+            "Indent recursive listings instead of printing every directory as a section \
+             (the -R output of earlier versions)",
+        ))
+        .arg(arg_bool_long(
+            "tree",
+            false,
+            "List subdirectories as a tree",
+        ))
+        .arg(
+            arg_base("charset", false, "Characters to draw the tree with")
+                .long("charset")
+                .value_parser(["unicode", "ascii"]),
+        )
+        .arg(arg_bool_long(
+            "tree-size",
+            false,
+            "Print the size of every entry of the tree",
+        ))
+        .arg(arg_bool_long(
+            "du",
+            false,
+            "Print the size of directories of the tree as the sum of their contents",
+        ))
+        .arg(
+            arg_bool_t(
+                "across",
+                false,
+                "List entries by lines instead of by columns",
+                'x',
+                false,
+            )
+            .overrides_with("commas"),
+        )
+        .arg(
+            arg_bool_t(
+                "commas",
+                false,
+                "Fill the width with a comma separated list",
+                'm',
+                false,
+            )
+            .overrides_with("across"),
+        )
+        .arg(
+            arg_base("quoting-style", false, "How to quote file names")
+                .long("quoting-style")
+                .value_parser(QuotingStyle::NAMES),
+        )
+        .arg(
+            arg_bool_t(
+                "escape",
+                false,
+                "Print C-style escapes for nongraphic characters",
+                'b',
+                false,
+            )
+            .overrides_with("quoting-style"),
+        )
+        .arg(arg_bool_t(
+            "hide-control-chars",
+            false,
+            "Print ? instead of nongraphic characters",
+            'q',
+            false,
+        ))
+        .arg(arg_bool_t(
+            "dereference",
+            false,
+            "Show information of link targets instead of the links",
+            'L',
+            false,
+        ))
         .arg(arg_bool("inode", false, "Add inode info to output", false))
         .arg(arg_bool_t("json", false, "Short json output", 'j', false))
         .arg(arg_bool_t(
@@ -66,26 +231,151 @@ pub fn ls_command() -> Command {
             'J',
             false,
         ))
+        .arg(arg_bool_long(
+            "compact",
+            false,
+            "Compact (single line) json output",
+        ))
+        .arg(
+            arg_bool_long("ndjson", false, "JSON Lines output, one entry per line")
+                .visible_alias("json-lines"),
+        )
+        .arg(arg_bool_long(
+            "schema",
+            false,
+            "Print the JSON Schema of json output and exit",
+        ))
+        .arg(
+            arg_base("color", false, "When to colorize the output")
+                .long("color")
+                .visible_alias("colour")
+                .num_args(0..=1)
+                .require_equals(true)
+                .default_missing_value("always")
+                .value_parser([
+                    "auto", "always", "never", "yes", "force", "tty", "if-tty", "no", "none",
+                ]),
+        )
+        .arg(arg_bool_long("csv", false, "Comma separated values output").conflicts_with("tsv"))
+        .arg(arg_bool_long("tsv", false, "Tab separated values output"))
+        .arg(
+            arg_base(
+                "columns",
+                false,
+                "Comma separated columns of csv/tsv output",
+            )
+            .long("columns")
+            .value_delimiter(',')
+            .value_parser(Column::NAMES),
+        )
+}
+
+// Patterns of `--ignore` or `--hide`. Globs of `--ignore` may be comma
+// separated (`\,` is a literal comma), regular expressions are taken whole.
+fn patterns(matches: &ArgMatches, id: &str) -> Vec<Pattern> {
+    let regex = *matches.get_one("regex").unwrap();
+    matches
+        .get_many::<String>(id)
+        .into_iter()
+        .flatten()
+        .flat_map(|value| {
+            if regex || id != "ignore" {
+                vec![value.as_str()]
+            } else {
+                split_unescaped(value, ',')
+            }
+        })
+        .filter(|pattern| !pattern.is_empty())
+        .map(|pattern| {
+            Pattern::new(pattern, regex).unwrap_or_else(|e| {
+                ls_command()
+                    .error(
+                        clap::error::ErrorKind::ValueValidation,
+                        format!("invalid pattern '{pattern}' for '--{id}': {e}"),
+                    )
+                    .exit()
+            })
+        })
+        .collect()
+}
+
+// Splits at every `separator` not escaped with a backslash; escapes are kept
+// for the glob to read.
+fn split_unescaped(s: &str, separator: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut start = 0;
+    let mut escaped = false;
+    for (i, c) in s.char_indices() {
+        if escaped {
+            escaped = false;
+        } else if c == '\\' {
+            escaped = true;
+        } else if c == separator {
+            parts.push(&s[start..i]);
+            start = i + c.len_utf8();
+        }
+    }
+    parts.push(&s[start..]);
+    parts
+}
+
+// Values of `--sort`, aliases are accepted but not listed.
+fn sort_keys() -> PossibleValuesParser {
+    PossibleValuesParser::new([
+        PossibleValue::new("name"),
+        PossibleValue::new("type"),
+        PossibleValue::new("version").alias("v"),
+        PossibleValue::new("extension").alias("ext"),
+        PossibleValue::new("size"),
+        PossibleValue::new("allocated").alias("blocks"),
+        PossibleValue::new("time").aliases(["mtime", "modified"]),
+        PossibleValue::new("created").aliases(["birth", "btime", "creation"]),
+        PossibleValue::new("ctime").alias("change"),
+        PossibleValue::new("atime").aliases(["access", "accessed"]),
+        PossibleValue::new("inode"),
+        PossibleValue::new("width"),
+        PossibleValue::new("none"),
+    ])
 }
 
 #[derive(Debug, Clone)]
 pub struct Config {
-    pub path: String,
+    // operands, never empty
+    pub paths: Vec<String>,
     pub cols: Option<usize>,
-    // comma separated
-    pub ignore: Option<String>,
+    pub ignore: Vec<Pattern>,
+    // ignored unless `all` is set
+    pub hide: Vec<Pattern>,
+    pub gitignore: bool,
+    // status column of the long format and `git_status` JSON field
+    pub git: bool,
     pub all: bool,
     pub long: bool,
     pub numeric: bool,
+    pub numeric_ids: bool,
     pub humanable: bool,
     pub reverse: bool,
-    pub sort_type: Option<SortType>,
-    // pub ext_sort: bool,
+    // most significant first, empty for directory order
+    pub sort_keys: Vec<SortType>,
+    pub group_directories_first: bool,
+    pub allocated: bool,
     pub recursive: Option<RecursionOptions>,
+    // `-R` as an indented list of names instead of `path:` sections
+    pub indent: bool,
     pub one_col: bool,
+    pub layout: Layout,
+    pub tree: Option<TreeOptions>,
+    pub dereference: bool,
+    pub quoting: Quoting,
     pub inode: bool,
     pub json_mini: bool,
     pub json_big: bool,
+    pub json_compact: bool,
+    pub json_lines: bool,
+    pub json_schema: bool,
+    pub color: ColorMode,
+    pub delimited: Option<Delimiter>,
+    pub columns: Vec<Column>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -95,65 +385,347 @@ pub enum RecursionOptions {
     No,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum SortType {
     Name,
+    // directories, files, links, then special files
+    Type,
+    // numbers in names by value, `file2` before `file10`
+    Version,
+    Extension,
     Size,
+    // disk usage instead of apparent size
+    Allocated,
+    // modification time
     Time,
+    // birth time, the modification time where there is none
+    Created,
+    // status change time, the modification time outside of unix
+    Changed,
+    Accessed,
+    Inode,
+    // display width of the name
+    Width,
+}
+
+impl SortType {
+    /// Key of a `--sort` value; `none` and unknown values have none.
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "name" => Some(SortType::Name),
+            "type" => Some(SortType::Type),
+            "version" | "v" => Some(SortType::Version),
+            "extension" | "ext" => Some(SortType::Extension),
+            "size" => Some(SortType::Size),
+            "allocated" | "blocks" => Some(SortType::Allocated),
+            "time" | "mtime" | "modified" => Some(SortType::Time),
+            "created" | "birth" | "btime" | "creation" => Some(SortType::Created),
+            "ctime" | "change" => Some(SortType::Changed),
+            "atime" | "access" | "accessed" => Some(SortType::Accessed),
+            "inode" => Some(SortType::Inode),
+            "width" => Some(SortType::Width),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorMode {
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorMode {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "auto" | "tty" | "if-tty" => Some(ColorMode::Auto),
+            "always" | "yes" | "force" => Some(ColorMode::Always),
+            "never" | "no" | "none" => Some(ColorMode::Never),
+            _ => None,
+        }
+    }
+    /// Decides whether to emit colours for this process, looking at `NO_COLOR`,
+    /// `CLICOLOR_FORCE` and whether stdout is a terminal.
+    pub fn enabled(&self) -> bool {
+        self.resolve(
+            env::var_os("NO_COLOR"),
+            env::var_os("CLICOLOR_FORCE"),
+            term::stdout_is_terminal(),
+        )
+    }
+    /// Explicit `always`/`never` win; in `auto` mode a non-empty `NO_COLOR`
+    /// disables colours, a `CLICOLOR_FORCE` other than `0` enables them, and
+    /// otherwise colours are used only on a terminal.
+    pub fn resolve(
+        &self,
+        no_color: Option<OsString>,
+        clicolor_force: Option<OsString>,
+        is_terminal: bool,
+    ) -> bool {
+        match self {
+            ColorMode::Always => true,
+            ColorMode::Never => false,
+            ColorMode::Auto => {
+                if no_color.is_some_and(|v| !v.is_empty()) {
+                    false
+                } else if clicolor_force.is_some_and(|v| !v.is_empty() && v != "0") {
+                    true
+                } else {
+                    is_terminal
+                }
+            }
+        }
+    }
+}
+
+/// How `--tree` draws the tree.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct TreeOptions {
+    pub charset: Charset,
+    // size in front of every entry
+    pub sizes: bool,
+    // directories sized by their contents, like `du`
+    pub aggregate: bool,
+}
+
+/// Characters of the tree connectors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Charset {
+    #[default]
+    Unicode,
+    Ascii,
+}
+
+impl Charset {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "unicode" | "utf8" | "utf-8" => Some(Charset::Unicode),
+            "ascii" => Some(Charset::Ascii),
+            _ => None,
+        }
+    }
+    /// ASCII if the locale names a character set other than UTF-8, so a
+    /// plain `C` or `POSIX` locale gets a readable tree too.
+    pub fn from_locale() -> Self {
+        let locale = ["LC_ALL", "LC_CTYPE", "LANG"]
+            .iter()
+            .filter_map(|var| env::var(var).ok())
+            .find(|value| !value.is_empty());
+        match locale {
+            Some(locale) => {
+                let locale = locale.to_lowercase();
+                if locale.contains("utf-8") || locale.contains("utf8") {
+                    Charset::Unicode
+                } else {
+                    Charset::Ascii
+                }
+            }
+            None => Charset::Unicode,
+        }
+    }
+}
+
+/// Arrangement of the names of a short listing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Layout {
+    // top to bottom, then left to right
+    #[default]
+    Columns,
+    // `-x`: left to right, then top to bottom
+    Across,
+    // `-m`
+    Commas,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Delimiter {
+    Csv,
+    Tsv,
+}
+
+impl Delimiter {
+    pub fn separator(&self) -> char {
+        match self {
+            Delimiter::Csv => ',',
+            Delimiter::Tsv => '\t',
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Column {
+    Name,
+    Path,
+    Size,
+    Mode,
+    Inode,
+    Mtime,
+    Ctime,
+    Type,
+    Target,
+}
+
+impl Column {
+    pub const NAMES: [&'static str; 9] = [
+        "name", "path", "size", "mode", "inode", "mtime", "ctime", "type", "target",
+    ];
+    pub const DEFAULT: [Column; 6] = [
+        Column::Name,
+        Column::Path,
+        Column::Type,
+        Column::Size,
+        Column::Mode,
+        Column::Mtime,
+    ];
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "name" => Some(Column::Name),
+            "path" => Some(Column::Path),
+            "size" => Some(Column::Size),
+            "mode" => Some(Column::Mode),
+            "inode" => Some(Column::Inode),
+            "mtime" => Some(Column::Mtime),
+            "ctime" => Some(Column::Ctime),
+            "type" => Some(Column::Type),
+            "target" => Some(Column::Target),
+            _ => None,
+        }
+    }
+    pub fn name(&self) -> &'static str {
+        match self {
+            Column::Name => "name",
+            Column::Path => "path",
+            Column::Size => "size",
+            Column::Mode => "mode",
+            Column::Inode => "inode",
+            Column::Mtime => "mtime",
+            Column::Ctime => "ctime",
+            Column::Type => "type",
+            Column::Target => "target",
+        }
+    }
 }
 
 impl Config {
     pub fn clap_parse(matches: &ArgMatches) -> Self {
-        let sort_type = if *matches.get_one("name").unwrap() {
-            Some(SortType::Name)
+        let sort_keys = if *matches.get_one("name").unwrap() {
+            vec![SortType::Name]
         } else if *matches.get_one("size").unwrap() {
-            Some(SortType::Size)
+            vec![SortType::Size]
         } else if *matches.get_one("time").unwrap() {
-            Some(SortType::Time)
+            vec![SortType::Time]
+        } else if *matches.get_one("ext").unwrap() {
+            vec![SortType::Extension]
+        } else if *matches.get_one("version-sort").unwrap() {
+            vec![SortType::Version]
         } else {
-            let sort = matches.get_one::<String>("sort");
-            if let Some(sort) = sort {
-                match sort.as_str() {
-                    "name" => Some(SortType::Name),
-                    "size" => Some(SortType::Size),
-                    "time" => Some(SortType::Time),
-                    _ => None,
-                }
-            } else {
-                None
-            }
+            // invalid values are rejected by the parser, `none` keeps the
+            // directory order
+            matches
+                .get_many::<String>("sort")
+                .into_iter()
+                .flatten()
+                .filter_map(|key| SortType::parse(key))
+                .collect()
         };
 
+        let numeric_ids = *matches.get_one("numeric-uid-gid").unwrap();
+        let du = *matches.get_one("du").unwrap();
+        let tree = matches
+            .get_one::<bool>("tree")
+            .unwrap()
+            .then(|| TreeOptions {
+                charset: matches
+                    .get_one::<String>("charset")
+                    .and_then(|c| Charset::parse(c))
+                    .unwrap_or_else(Charset::from_locale),
+                sizes: *matches.get_one::<bool>("tree-size").unwrap() || du,
+                aggregate: du,
+            });
+
         Self {
-            path: matches
-                .get_one::<String>("path")
-                .unwrap_or(&".".to_string())
-                .to_string(),
+            paths: matches
+                .get_many::<String>("path")
+                .map(|paths| paths.cloned().collect())
+                .unwrap_or_else(|| vec![".".to_string()]),
             cols: matches
                 .get_one::<String>("cols")
                 .map(|s| s.parse().unwrap_or(0)),
-            ignore: matches.get_one::<String>("ignore").cloned(),
+            ignore: patterns(matches, "ignore"),
+            hide: patterns(matches, "hide"),
+            gitignore: *matches.get_one("gitignore").unwrap(),
+            git: *matches.get_one("git").unwrap(),
             all: *matches.get_one("all").unwrap(),
-            long: *matches.get_one("long").unwrap(),
+            // like `ls -n`, numeric ids imply the long format
+            long: *matches.get_one::<bool>("long").unwrap() || numeric_ids,
             numeric: *matches.get_one("numeric").unwrap(),
+            numeric_ids,
             humanable: *matches.get_one("humanable").unwrap(),
             reverse: *matches.get_one("reverse").unwrap(),
-            sort_type,
-            // ext_sort: *matches.get_one("ext").unwrap(),
-            recursive: matches.get_one::<String>("recursive").map(|depth| {
-                let depth = depth.to_lowercase();
-                if depth == "max" || depth.contains("unlim") {
-                    RecursionOptions::Unlimited
-                } else if let Ok(depth) = depth.parse() {
-                    RecursionOptions::Depth(depth)
-                } else {
-                    RecursionOptions::No
-                }
-            }),
+            sort_keys,
+            group_directories_first: *matches.get_one("group-directories-first").unwrap(),
+            allocated: *matches.get_one("allocated").unwrap(),
+            recursive: matches
+                .get_one::<String>("recursive")
+                .map(|depth| {
+                    let depth = depth.to_lowercase();
+                    if depth == "max" || depth.contains("unlim") {
+                        RecursionOptions::Unlimited
+                    } else if let Ok(depth) = depth.parse() {
+                        RecursionOptions::Depth(depth)
+                    } else {
+                        RecursionOptions::No
+                    }
+                })
+                // a tree goes all the way down unless `-R` limits it
+                .or(tree.map(|_| RecursionOptions::Unlimited)),
+            indent: *matches.get_one("indent").unwrap(),
             one_col: *matches.get_one("one").unwrap(),
+            tree,
+            layout: if *matches.get_one("commas").unwrap() {
+                Layout::Commas
+            } else if *matches.get_one("across").unwrap() {
+                Layout::Across
+            } else {
+                Layout::Columns
+            },
+            dereference: *matches.get_one("dereference").unwrap(),
+            quoting: Quoting::new(
+                if *matches.get_one("escape").unwrap() {
+                    QuotingStyle::Escape
+                } else {
+                    matches
+                        .get_one::<String>("quoting-style")
+                        .and_then(|s| QuotingStyle::parse(s))
+                        .or_else(QuotingStyle::from_env)
+                        .unwrap_or_default()
+                },
+                *matches.get_one("hide-control-chars").unwrap(),
+            ),
             inode: *matches.get_one("inode").unwrap(),
             json_mini: *matches.get_one("json").unwrap(),
             json_big: *matches.get_one("JSON").unwrap(),
+            json_compact: *matches.get_one("compact").unwrap(),
+            json_lines: *matches.get_one("ndjson").unwrap(),
+            json_schema: *matches.get_one("schema").unwrap(),
+            color: matches
+                .get_one::<String>("color")
+                .and_then(|c| ColorMode::parse(c))
+                .unwrap_or_default(),
+            delimited: if *matches.get_one("csv").unwrap() {
+                Some(Delimiter::Csv)
+            } else if *matches.get_one("tsv").unwrap() {
+                Some(Delimiter::Tsv)
+            } else {
+                None
+            },
+            columns: matches
+                .get_many::<String>("columns")
+                .map(|cols| cols.filter_map(|c| Column::parse(c)).collect())
+                .unwrap_or_else(|| Column::DEFAULT.to_vec()),
         }
     }
 }
@@ -1,8 +1,19 @@
+use std::{
+    io::{self, Write},
+    path::PathBuf,
+};
+
 use crate::{
-    command::{Config, RecursionOptions, SortType},
-    files::FileSystemEntry,
-    json::Serializer,
+    command::{Charset, Column, Config, Delimiter, Layout, RecursionOptions, TreeOptions},
+    diagnostics::{Diagnostic, Diagnostics},
+    error::LsError,
+    files::{self, FileSystemEntry, LongWidths, Walk},
+    json::{JsonOptions, JsonWriter, Serializer},
+    pattern::Pattern,
+    quoting::Quoting,
+    sort::Order,
     term,
+    width::{self, Grid},
 };
 
 #[derive(Debug, Clone)]
@@ -32,30 +43,28 @@ impl DataProcessor {
         self.entries.as_slice()
     }
 
-    pub fn filter(mut self) -> Self {
+    pub fn into_entries(self) -> Vec<FileSystemEntry> {
         self.entries
-            .retain(|fse| self.config.all || !fse.is_hidden());
-        self.entries.retain(|fse| {
-            self.config.ignore.is_none()
-                || !self.config.ignore.as_ref().unwrap().contains(fse.name())
-        });
+    }
+
+    pub fn filter(mut self) -> Self {
+        self.entries.retain(|fse| Self::keeps(&self.config, fse));
 
         self
     }
 
+    /// Whether listings show `fse`: dot files and entries matching `--hide`
+    /// only with `-a`, entries matching `--ignore` never.
+    pub fn keeps(config: &Config, fse: &FileSystemEntry) -> bool {
+        let name = fse.os_name();
+        let path = &fse.base_info().path;
+        let matches = |patterns: &[Pattern]| patterns.iter().any(|p| p.matches(name, path));
+
+        (config.all || !fse.is_hidden() && !matches(&config.hide)) && !matches(&config.ignore)
+    }
+
     pub fn sort(mut self) -> Self {
-        if let Some(sort_type) = self.config.sort_type.as_ref() {
-            match sort_type {
-                SortType::Time => self
-                    .entries
-                    .sort_by_key(|fse| fse.metadata().modified_at.clone()),
-                SortType::Size => self.entries.sort_by_key(|fse| fse.metadata().size),
-                SortType::Name => self.entries.sort_by_key(|fse| fse.cname()),
-            }
-        }
-        if self.config.reverse {
-            self.entries.reverse();
-        }
+        Order::new(&self.config).apply(&mut self.entries);
         self
     }
 
@@ -66,60 +75,90 @@ impl DataProcessor {
 
 pub struct PreparedData {
     names: Vec<String>,
+    // `total N` line printed above the entries
+    total: Option<String>,
 }
 
 impl PreparedData {
     pub fn new(entries: Vec<FileSystemEntry>, config: Config) -> Self {
+        let colored = config.color.enabled();
         let names = if config.long {
-            Self::prepare_long(&entries, &config)
+            Self::prepare_long(&entries, &config, colored)
         } else {
-            Self::prepare_short(&entries, &config)
+            Self::prepare_short(&entries, &config, colored)
         };
 
+        let names = if config.allocated {
+            Self::add_allocated(&entries, names, config.humanable)
+        } else {
+            names
+        };
         let names = if config.numeric {
             Self::add_numbers(names)
         } else {
             names
         };
-        Self { names }
+
+        let total = (config.long || config.allocated).then(|| {
+            let bytes = entries
+                .iter()
+                .map(|fse| fse.metadata().allocated_size())
+                .sum();
+            format!("total {}", files::allocated_string(bytes, config.humanable))
+        });
+        Self { names, total }
     }
     pub fn get_names(&self) -> &[String] {
         self.names.as_slice()
     }
-
-    fn prepare_short(entries: &[FileSystemEntry], _config: &Config) -> Vec<String> {
-        entries.iter().map(|fse| fse.to_string_short()).collect()
+    pub fn get_total(&self) -> Option<&str> {
+        self.total.as_deref()
     }
 
-    fn prepare_long(entries: &[FileSystemEntry], config: &Config) -> Vec<String> {
-        let max_time = entries
+    fn prepare_short(entries: &[FileSystemEntry], config: &Config, colored: bool) -> Vec<String> {
+        entries
             .iter()
-            .map(|fse| fse.metadata().modified_at.format().to_string().len())
-            .max()
-            .unwrap_or(0);
+            .map(|fse| fse.to_string_short(colored, config.quoting))
+            .collect()
+    }
 
-        let max_size = entries
-            .iter()
-            .map(|fse| {
-                if config.humanable {
-                    fse.metadata().human_size.len()
-                } else {
-                    fse.metadata().size.to_string().len()
-                }
-            })
-            .max()
-            .unwrap_or(0);
+    fn prepare_long(entries: &[FileSystemEntry], config: &Config, colored: bool) -> Vec<String> {
+        let widths = LongWidths::new(entries, config);
 
         entries
             .iter()
             .map(|fse| {
                 #[cfg(unix)]
-                return fse.to_string_long(config.humanable, config.inode, max_size, max_time);
+                return fse.to_string_long(
+                    config.humanable,
+                    config.inode,
+                    config.numeric_ids,
+                    &widths,
+                    colored,
+                    config.quoting,
+                );
                 #[cfg(windows)]
-                return fse.to_string_long(config.humanable, max_size, max_time);
+                return fse.to_string_long(config.humanable, &widths, colored, config.quoting);
             })
             .collect()
     }
+    fn add_allocated(
+        entries: &[FileSystemEntry],
+        names: Vec<String>,
+        humanable: bool,
+    ) -> Vec<String> {
+        let sizes: Vec<_> = entries
+            .iter()
+            .map(|fse| fse.metadata().allocated_string(humanable))
+            .collect();
+        let width = sizes.iter().map(|s| s.len()).max().unwrap_or(0);
+
+        sizes
+            .iter()
+            .zip(names)
+            .map(|(size, name)| format!("{size:>width$} {name}"))
+            .collect()
+    }
     fn add_numbers(names: Vec<String>) -> Vec<String> {
         names
             .iter()
@@ -131,25 +170,52 @@ impl PreparedData {
 
 pub trait OutputFormatter {
     fn format(&self) -> String;
+    fn write(&self, out: &mut dyn Write) -> io::Result<()> {
+        writeln!(out, "{}", self.format())
+    }
 }
 
 pub struct TextFormatter {
     names: Vec<String>,
+    total: Option<String>,
     long: bool,
     cols: Option<usize>,
+    layout: Layout,
 }
 impl OutputFormatter for TextFormatter {
     fn format(&self) -> String {
-        if self.long {
+        let body = if self.long {
             self.format_long()
         } else {
             self.format_short()
+        };
+        match self.total.as_ref() {
+            Some(total) if body.is_empty() => total.clone(),
+            Some(total) => format!("{total}\n{body}"),
+            None => body,
         }
     }
+    fn write(&self, out: &mut dyn Write) -> io::Result<()> {
+        // an empty directory prints nothing, not even a line break
+        let text = self.format();
+        if text.is_empty() {
+            return Ok(());
+        }
+        writeln!(out, "{text}")
+    }
 }
 impl TextFormatter {
-    fn new(names: Vec<String>, long: bool, cols: Option<usize>) -> Self {
-        Self { names, long, cols }
+    fn new(prepared: PreparedData, config: &Config) -> Self {
+        Self {
+            names: prepared.names,
+            total: prepared.total,
+            long: config.long,
+            cols: config.cols,
+            layout: config.layout,
+        }
+    }
+    fn name_widths(&self) -> Vec<usize> {
+        self.names.iter().map(|n| width::display_width(n)).collect()
     }
     fn format_with_cols(&self, cols: usize) -> String {
         if self.names.is_empty() {
@@ -160,69 +226,44 @@ impl TextFormatter {
             return self.names.join(" ");
         }
 
-        let max_width = self.names.iter().map(|n| n.len()).max().unwrap_or(1);
-        let col_width = max_width + 2;
-        let total_items = self.names.len();
-        let rows = total_items.div_ceil(cols);
-
-        let mut output = String::new();
-        for row in 0..rows {
-            let mut line = String::new();
-
-            for col in 0..cols {
-                let idx = col * rows + row;
-                if idx < total_items {
-                    let name = &self.names[idx];
-                    let temp_col_width = col_width - if name.starts_with("\x1b") { 0 } else { 9 };
-                    line.push_str(&format!("{name:<temp_col_width$}"));
-                }
-            }
-
-            output.push_str(line.trim_end());
-            output.push('\n');
-        }
-
-        output.trim_end().to_string()
+        let widths = self.name_widths();
+        let grid = if self.layout == Layout::Across {
+            Grid::across_with_cols(&widths, cols)
+        } else {
+            Grid::with_cols(&widths, cols)
+        };
+        grid.render(&self.names)
     }
     fn format_with_terminal_width(&self) -> String {
         if self.names.is_empty() {
             return String::new();
         }
 
-        let (term_cols, _) = term::terminal_size().unwrap_or((80, 24));
-        let term_cols = term_cols as usize;
-
-        let total_width = self.names.iter().map(|n| n.len()).sum::<usize>() + self.names.len() - 1;
+        let term_cols = Self::terminal_width();
+        let widths = self.name_widths();
+        let total_width = widths.iter().sum::<usize>() + widths.len() - 1;
         if total_width <= term_cols {
             return self.names.join(" ");
         }
 
-        let max_width = self.names.iter().map(|n| n.len()).max().unwrap_or(1);
-        let col_width = max_width + 2;
-        let max_cols = (term_cols / col_width).max(1);
-        let total_items = self.names.len();
-        let rows = total_items.div_ceil(max_cols);
-
-        let mut output = String::new();
-        for row in 0..rows {
-            let mut line = String::new();
-
-            for col in 0..max_cols {
-                let idx = col * rows + row;
-                if idx < total_items {
-                    let name = &self.names[idx];
-                    line.push_str(&format!("{name:<col_width$}"));
-                }
-            }
-
-            output.push_str(line.trim_end());
-            output.push('\n');
-        }
-
-        output.trim_end().to_string()
+        Grid::fit(&widths, term_cols, self.layout == Layout::Across).render(&self.names)
+    }
+    fn format_commas(&self) -> String {
+        // an explicit number of names per line lifts the width limit
+        let line_width = match self.cols {
+            Some(_) => usize::MAX,
+            None => Self::terminal_width(),
+        };
+        width::commas(&self.names, line_width, self.cols)
+    }
+    fn terminal_width() -> usize {
+        let (term_cols, _) = term::terminal_size().unwrap_or((80, 24));
+        term_cols as usize
     }
     fn format_short(&self) -> String {
-        if let Some(cols) = self.cols {
+        if self.layout == Layout::Commas {
+            self.format_commas()
+        } else if let Some(cols) = self.cols {
             self.format_with_cols(cols)
         } else {
             self.format_with_terminal_width()
@@ -234,22 +275,216 @@ impl TextFormatter {
 }
 
 pub struct JsonFormatter {
-    // for serializing
-    entry: FileSystemEntry,
+    // for serializing, written as an array if there is more than one
+    entries: Vec<FileSystemEntry>,
     mini: bool,
+    pretty: bool,
+    options: JsonOptions,
 }
 impl JsonFormatter {
-    pub fn new(entry: FileSystemEntry, mini: bool) -> Self {
-        Self { entry, mini }
+    pub fn new(entry: FileSystemEntry, mini: bool, pretty: bool) -> Self {
+        Self::many(vec![entry], mini, pretty)
+    }
+    pub fn many(entries: Vec<FileSystemEntry>, mini: bool, pretty: bool) -> Self {
+        Self {
+            entries,
+            mini,
+            pretty,
+            options: JsonOptions::default(),
+        }
+    }
+    pub fn with_git_status(mut self, git_status: bool) -> Self {
+        self.options.git_status = git_status;
+        self
+    }
+    fn write_json<W: Write>(&self, out: W) -> io::Result<W> {
+        let mut writer = JsonWriter::new(out, self.pretty);
+        let array = self.entries.len() != 1;
+        if array {
+            writer.begin_array()?;
+        }
+        for entry in &self.entries {
+            if self.mini {
+                entry.write_short_json(&mut writer, self.options)?;
+            } else {
+                entry.write_long_json(&mut writer, self.options)?;
+            }
+        }
+        if array {
+            writer.end_array()?;
+        }
+        Ok(writer.into_inner())
     }
 }
 impl OutputFormatter for JsonFormatter {
     fn format(&self) -> String {
-        if self.mini {
-            self.entry.short_json()
-        } else {
-            self.entry.long_json()
+        // writing into a `Vec` cannot fail
+        let buf = self.write_json(Vec::new()).unwrap_or_default();
+        String::from_utf8_lossy(&buf).into_owned()
+    }
+    fn write(&self, out: &mut dyn Write) -> io::Result<()> {
+        let out = self.write_json(out)?;
+        out.write_all(b"\n")
+    }
+}
+
+fn column_value(fse: &FileSystemEntry, column: Column, humanable: bool) -> String {
+    let md = fse.metadata();
+    match column {
+        Column::Name => fse.cname(),
+        Column::Path => fse.base_info().path.display().to_string(),
+        Column::Size => {
+            if humanable {
+                md.human_size.clone()
+            } else {
+                md.size.to_string()
+            }
         }
+        Column::Mode => md.mode_str.clone(),
+        #[cfg(unix)]
+        Column::Inode => md.inode.to_string(),
+        #[cfg(windows)]
+        Column::Inode => String::new(),
+        Column::Mtime => md.modified_at.to_iso8601(),
+        #[cfg(unix)]
+        Column::Ctime => md.changed_at.to_iso8601(),
+        // no status change time, like `--sort ctime`
+        #[cfg(windows)]
+        Column::Ctime => md.modified_at.to_iso8601(),
+        Column::Type => fse.type_name().to_string(),
+        Column::Target => match fse {
+            FileSystemEntry::Link { target, .. } => target.display().to_string(),
+            _ => String::new(),
+        },
+    }
+}
+
+// Quotes a field the RFC 4180 way when it contains the separator, a quote or a line break.
+fn quote_field(field: &str, separator: char) -> String {
+    if field.contains([separator, '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// CSV or TSV output: a header line with the column names, then one line per
+/// entry with fields quoted as needed.
+pub struct DelimitedFormatter {
+    entries: Vec<FileSystemEntry>,
+    columns: Vec<Column>,
+    humanable: bool,
+    delimiter: Delimiter,
+}
+impl DelimitedFormatter {
+    pub fn new(
+        entries: Vec<FileSystemEntry>,
+        columns: Vec<Column>,
+        humanable: bool,
+        delimiter: Delimiter,
+    ) -> Self {
+        Self {
+            entries,
+            columns,
+            humanable,
+            delimiter,
+        }
+    }
+}
+impl OutputFormatter for DelimitedFormatter {
+    fn format(&self) -> String {
+        let separator = self.delimiter.separator();
+        let separator_str = separator.to_string();
+
+        let mut lines = Vec::with_capacity(self.entries.len() + 1);
+        lines.push(
+            self.columns
+                .iter()
+                .map(|c| c.name())
+                .collect::<Vec<_>>()
+                .join(&separator_str),
+        );
+        for fse in &self.entries {
+            lines.push(
+                self.columns
+                    .iter()
+                    .map(|&c| quote_field(&column_value(fse, c, self.humanable), separator))
+                    .collect::<Vec<_>>()
+                    .join(&separator_str),
+            );
+        }
+
+        lines.join("\n")
+    }
+}
+
+pub struct JsonLinesFormatter<'a> {
+    // start entry, read lazily while writing
+    entry: FileSystemEntry,
+    recursive: Option<usize>,
+    config: Config,
+    // problems met while reading
+    diagnostics: &'a Diagnostics,
+}
+impl<'a> JsonLinesFormatter<'a> {
+    pub fn new(entry: FileSystemEntry, config: &Config, diagnostics: &'a Diagnostics) -> Self {
+        Self {
+            entry,
+            recursive: FileSystemEntry::recursion_depth(config),
+            config: config.clone(),
+            diagnostics,
+        }
+    }
+    fn write_line(
+        &self,
+        out: &mut dyn Write,
+        entry: &FileSystemEntry,
+        depth: usize,
+    ) -> io::Result<()> {
+        let options = JsonOptions {
+            git_status: self.config.git,
+        };
+        entry.write_json_line(&mut JsonWriter::new(&mut *out, false), depth, options)?;
+        out.write_all(b"\n")?;
+        out.flush()
+    }
+}
+impl OutputFormatter for JsonLinesFormatter<'_> {
+    fn format(&self) -> String {
+        let mut buf = Vec::new();
+        // writing into a `Vec` cannot fail, only reading the directory can
+        let _ = self.write(&mut buf);
+        String::from_utf8_lossy(&buf).trim_end().to_string()
+    }
+    fn write(&self, out: &mut dyn Write) -> io::Result<()> {
+        self.write_line(out, &self.entry, 0)?;
+
+        // entries are reported before their contents, so the contents of a
+        // skipped directory are the deeper entries that follow it
+        let mut skipped_at: Option<usize> = None;
+        let mut root = self.entry.clone();
+        root.walk_start_dir(
+            self.recursive,
+            Walk {
+                order: Order::requested(&self.config),
+                ..Walk::new(&self.config, false, self.diagnostics)
+            },
+            &mut |fse, depth| {
+                if skipped_at.is_some_and(|skipped| depth > skipped) {
+                    return Ok(());
+                }
+                skipped_at = None;
+                if !DataProcessor::keeps(&self.config, fse) {
+                    skipped_at = Some(depth);
+                    return Ok(());
+                }
+                Ok(self.write_line(out, fse, depth)?)
+            },
+        )
+        .map_err(|e| match e {
+            LsError::IOError(e) => e,
+            e => io::Error::other(e.to_string()),
+        })
     }
 }
 
@@ -257,7 +492,9 @@ pub struct RecursiveFormatter {
     entry: FileSystemEntry,
     depth: usize,
     max_depth: Option<usize>,
-    ignore: Option<String>,
+    config: Config,
+    colored: bool,
+    quoting: Quoting,
 }
 impl OutputFormatter for RecursiveFormatter {
     fn format(&self) -> String {
@@ -275,19 +512,23 @@ impl RecursiveFormatter {
                 RecursionOptions::Unlimited => None,
                 RecursionOptions::No => Some(0),
             },
-            ignore: config.ignore.clone(),
+            config: config.clone(),
+            colored: config.color.enabled(),
+            quoting: config.quoting,
         }
     }
     fn format_recursive(&self, entry: &FileSystemEntry, current_depth: usize) -> String {
-        if let Some(ignore) = self.ignore.as_ref() {
-            if ignore.contains(entry.name()) {
-                return String::new();
-            }
+        if current_depth > 0 && !DataProcessor::keeps(&self.config, entry) {
+            return String::new();
         }
         let mut output = String::new();
         let indent = "  ".repeat(current_depth);
 
-        output.push_str(&format!("{}{}\n", indent, entry.get_styled_name()));
+        output.push_str(&format!(
+            "{}{}\n",
+            indent,
+            entry.styled_name_quoted(self.colored, self.quoting)
+        ));
 
         if entry.is_dir() {
             let should_expand = if let Some(max_depth) = self.max_depth {
@@ -296,11 +537,10 @@ impl RecursiveFormatter {
                 true
             };
 
-            if should_expand {
-                if let Some(dir_entries) = entry.get_dir_entries() {
-                    for fse in dir_entries {
-                        output.push_str(&self.format_recursive(&fse, current_depth + 1));
-                    }
+            if should_expand && let Some(mut dir_entries) = entry.get_dir_entries() {
+                Order::new(&self.config).apply(&mut dir_entries);
+                for fse in dir_entries {
+                    output.push_str(&self.format_recursive(&fse, current_depth + 1));
                 }
             }
         }
@@ -309,35 +549,385 @@ impl RecursiveFormatter {
     }
 }
 
-pub struct Printer {
-    formatter: Box<dyn OutputFormatter>,
+/// `--tree` output: every directory followed by its filtered and sorted
+/// contents, drawn with connectors, and a count of directories and files.
+pub struct TreeFormatter {
+    entry: FileSystemEntry,
+    options: TreeOptions,
+    config: Config,
+}
+impl OutputFormatter for TreeFormatter {
+    fn format(&self) -> String {
+        let mut counts = (0, 0);
+        let (lines, size) = self.format_children(&self.entry, "", &mut counts);
+        let mut output = format!(
+            "{}{}\n",
+            self.size_prefix(&self.entry, size),
+            self.name(&self.entry)
+        );
+        output.push_str(&lines);
+
+        let (dirs, files) = counts;
+        output.push_str(&format!(
+            "\n{dirs} {}, {files} {}",
+            if dirs == 1 {
+                "directory"
+            } else {
+                "directories"
+            },
+            if files == 1 { "file" } else { "files" },
+        ));
+        output
+    }
+}
+
+impl TreeFormatter {
+    fn new(entry: FileSystemEntry, options: TreeOptions, config: &Config) -> Self {
+        Self {
+            entry,
+            options,
+            config: config.clone(),
+        }
+    }
+    // connectors of the last child, of the others, and the prefixes they leave
+    // for the children's children
+    fn connectors(&self) -> [&'static str; 4] {
+        match self.options.charset {
+            Charset::Unicode => ["└── ", "├── ", "    ", "│   "],
+            Charset::Ascii => ["`-- ", "|-- ", "    ", "|   "],
+        }
+    }
+    fn name(&self, entry: &FileSystemEntry) -> String {
+        entry.to_string_short(self.config.color.enabled(), self.config.quoting)
+    }
+    fn size_prefix(&self, entry: &FileSystemEntry, total: u64) -> String {
+        if !self.options.sizes {
+            return String::new();
+        }
+        let size = if self.options.aggregate && entry.is_dir() {
+            files::size_string(total, self.config.humanable)
+        } else {
+            entry.size_string(self.config.humanable)
+        };
+        format!("[{size:>7}]  ")
+    }
+    // Lines of the contents of `dir` and its size including them; `counts`
+    // collects the directories and files seen.
+    fn format_children(
+        &self,
+        dir: &FileSystemEntry,
+        prefix: &str,
+        counts: &mut (usize, usize),
+    ) -> (String, u64) {
+        let [last_connector, connector, last_indent, indent] = self.connectors();
+        let children = DataProcessor::new(
+            dir.get_dir_entries().unwrap_or_default(),
+            self.config.clone(),
+        )
+        .filter()
+        .sort()
+        .into_entries();
+
+        let mut output = String::new();
+        let mut total = dir.metadata().size;
+        let count = children.len();
+        for (i, child) in children.iter().enumerate() {
+            let last = i + 1 == count;
+            let (lines, size) = if child.is_dir() {
+                counts.0 += 1;
+                let child_prefix = format!("{prefix}{}", if last { last_indent } else { indent });
+                self.format_children(child, &child_prefix, counts)
+            } else {
+                counts.1 += 1;
+                (String::new(), child.metadata().size)
+            };
+            total += size;
+            output.push_str(&format!(
+                "{prefix}{}{}{}\n",
+                if last { last_connector } else { connector },
+                self.size_prefix(child, size),
+                self.name(child)
+            ));
+            output.push_str(&lines);
+        }
+        (output, total)
+    }
+}
+
+// Output of several operands, one formatter each.
+pub struct SectionsFormatter<'a> {
+    // optional `header:` line and the section itself
+    sections: Vec<(Option<String>, Box<dyn OutputFormatter + 'a>)>,
+    // blank line between sections
+    separated: bool,
+}
+impl OutputFormatter for SectionsFormatter<'_> {
+    fn format(&self) -> String {
+        let mut buf = Vec::new();
+        // writing into a `Vec` cannot fail
+        let _ = self.write(&mut buf);
+        String::from_utf8_lossy(&buf).trim_end().to_string()
+    }
+    fn write(&self, out: &mut dyn Write) -> io::Result<()> {
+        for (i, (header, formatter)) in self.sections.iter().enumerate() {
+            if self.separated && i > 0 {
+                writeln!(out)?;
+            }
+            if let Some(header) = header {
+                writeln!(out, "{header}:")?;
+            }
+            formatter.write(out)?;
+        }
+        Ok(())
+    }
+}
+
+pub struct Printer<'a> {
+    formatter: Box<dyn OutputFormatter + 'a>,
+    // problems met while printing, writing included
+    diagnostics: &'a Diagnostics,
 }
 
-impl Printer {
-    pub fn new(start_dir: FileSystemEntry, config: Config) -> Self {
-        let formatter: Box<dyn OutputFormatter> = match (
+impl<'a> Printer<'a> {
+    pub fn new(start_dir: FileSystemEntry, config: Config, diagnostics: &'a Diagnostics) -> Self {
+        let formatter: Box<dyn OutputFormatter + 'a> = match (
+            config.json_lines,
             config.json_mini,
             config.json_big,
+            config.delimited,
             config.recursive.is_some(),
         ) {
-            (true, _, _) => Box::new(JsonFormatter::new(start_dir, true)),
-            (_, true, _) => Box::new(JsonFormatter::new(start_dir, false)),
-            (_, _, true) => Box::new(RecursiveFormatter::new(start_dir, &config)),
+            (true, _, _, _, _) => {
+                Box::new(JsonLinesFormatter::new(start_dir, &config, diagnostics))
+            }
+            (_, true, _, _, _) => Box::new(
+                JsonFormatter::new(
+                    Self::filtered(start_dir, &config),
+                    true,
+                    !config.json_compact,
+                )
+                .with_git_status(config.git),
+            ),
+            (_, _, true, _, _) => Box::new(
+                JsonFormatter::new(
+                    Self::filtered(start_dir, &config),
+                    false,
+                    !config.json_compact,
+                )
+                .with_git_status(config.git),
+            ),
+            (_, _, _, Some(delimiter), _) => {
+                let entries = Self::flatten_entries(&start_dir, &config);
+                let columns = config.columns.clone();
+                Box::new(DelimitedFormatter::new(
+                    entries,
+                    columns,
+                    config.humanable,
+                    delimiter,
+                ))
+            }
+            (_, _, _, _, true) => match config.tree {
+                Some(options) => Box::new(TreeFormatter::new(start_dir, options, &config)),
+                None if config.indent => Box::new(RecursiveFormatter::new(start_dir, &config)),
+                None => {
+                    let path = start_dir.base_info().path.clone();
+                    Self::recursive_formatter(path, start_dir, &config)
+                }
+            },
             _ => {
-                let long = config.long;
-                let cols = config.cols;
-
-                let processor =
-                    DataProcessor::new(start_dir.get_dir_entries().unwrap_or_default(), config);
+                let processor = DataProcessor::new(
+                    start_dir.get_dir_entries().unwrap_or_default(),
+                    config.clone(),
+                );
 
                 let prepared_data = processor.filter().sort().prepare();
-                Box::new(TextFormatter::new(prepared_data.names, long, cols))
+                Box::new(TextFormatter::new(prepared_data, &config))
             }
         };
 
-        Self { formatter }
+        Self {
+            formatter,
+            diagnostics,
+        }
     }
+    /// Printer of the operands of the command line, each with the path it was
+    /// given as. Like GNU ls, files are listed first, in one block, then every
+    /// directory under a `path:` header when there is more than one operand.
+    pub fn with_operands(
+        operands: Vec<(String, FileSystemEntry)>,
+        config: Config,
+        diagnostics: &'a Diagnostics,
+    ) -> Self {
+        let (dirs, files): (Vec<_>, Vec<_>) =
+            operands.into_iter().partition(|(_, fse)| fse.is_dir());
+
+        let formatter: Box<dyn OutputFormatter + 'a> = match (
+            config.json_lines,
+            config.json_mini || config.json_big,
+            config.delimited,
+        ) {
+            // one stream / document / table for everything
+            (true, _, _) => Box::new(SectionsFormatter {
+                sections: files
+                    .into_iter()
+                    .chain(dirs)
+                    .map(|(_, fse)| (None, Self::new(fse, config.clone(), diagnostics).formatter))
+                    .collect(),
+                separated: false,
+            }),
+            (_, true, _) => Box::new(
+                JsonFormatter::many(
+                    files
+                        .into_iter()
+                        .chain(dirs)
+                        .map(|(_, fse)| Self::filtered(fse, &config))
+                        .collect(),
+                    config.json_mini,
+                    !config.json_compact,
+                )
+                .with_git_status(config.git),
+            ),
+            (_, _, Some(delimiter)) => {
+                let mut entries: Vec<_> = files.into_iter().map(|(_, fse)| fse).collect();
+                for (_, dir) in &dirs {
+                    entries.extend(Self::flatten_entries(dir, &config));
+                }
+                let columns = config.columns.clone();
+                Box::new(DelimitedFormatter::new(
+                    entries,
+                    columns,
+                    config.humanable,
+                    delimiter,
+                ))
+            }
+            _ => {
+                let headers = files.len() + dirs.len() > 1 && config.recursive.is_none();
+                let mut sections = Vec::new();
+                if !files.is_empty() {
+                    sections.push((None, Self::files_formatter(files, &config)));
+                }
+                let sectioned =
+                    config.recursive.is_some() && config.tree.is_none() && !config.indent;
+                for (operand, dir) in dirs {
+                    if sectioned {
+                        // headers named after the operand, not the absolute path
+                        sections.push((
+                            None,
+                            Self::recursive_formatter(operand.into(), dir, &config),
+                        ));
+                    } else {
+                        sections.push((
+                            headers.then_some(operand),
+                            Self::new(dir, config.clone(), diagnostics).formatter,
+                        ));
+                    }
+                }
+                Box::new(SectionsFormatter {
+                    sections,
+                    separated: true,
+                })
+            }
+        };
+
+        Self {
+            formatter,
+            diagnostics,
+        }
+    }
+    // `entry` without the entries listings do not show, in listing order, at
+    // every level.
+    fn filtered(mut entry: FileSystemEntry, config: &Config) -> FileSystemEntry {
+        entry.retain_entries(&mut |fse| DataProcessor::keeps(config, fse));
+        entry.arrange_entries(&mut |entries| Order::new(config).apply(entries));
+        entry
+    }
+    // Non-directory operands, sorted but never filtered out.
+    fn files_formatter(
+        files: Vec<(String, FileSystemEntry)>,
+        config: &Config,
+    ) -> Box<dyn OutputFormatter> {
+        let entries = files.into_iter().map(|(_, fse)| fse).collect();
+        let prepared_data = DataProcessor::new(entries, config.clone()).sort().prepare();
+        // the `total` line only sums directory contents
+        let prepared_data = PreparedData {
+            total: None,
+            ..prepared_data
+        };
+        Box::new(TextFormatter::new(prepared_data, config))
+    }
+    // `ls -R` output: a `path:` section of `dir` in the normal layout, then one
+    // for each of its (filtered and sorted) subdirectories, depth first.
+    fn recursive_formatter(
+        path: PathBuf,
+        dir: FileSystemEntry,
+        config: &Config,
+    ) -> Box<dyn OutputFormatter> {
+        let mut sections = Vec::new();
+        let max_depth = FileSystemEntry::recursion_depth(config).unwrap_or(0);
+        Self::push_sections(path, &dir, config, max_depth, &mut sections);
+        Box::new(SectionsFormatter {
+            sections,
+            separated: true,
+        })
+    }
+    fn push_sections(
+        path: PathBuf,
+        dir: &FileSystemEntry,
+        config: &Config,
+        // levels of subdirectories that were read
+        depth: usize,
+        sections: &mut Vec<(Option<String>, Box<dyn OutputFormatter>)>,
+    ) {
+        let entries = DataProcessor::new(dir.get_dir_entries().unwrap_or_default(), config.clone())
+            .filter()
+            .sort()
+            .into_entries();
+        let subdirs: Vec<_> = entries.iter().filter(|fse| fse.is_dir()).cloned().collect();
+
+        let prepared_data = PreparedData::new(entries, config.clone());
+        sections.push((
+            Some(config.quoting.quote(path.as_os_str())),
+            Box::new(TextFormatter::new(prepared_data, config)),
+        ));
+        if depth <= 1 {
+            return;
+        }
+        for subdir in subdirs {
+            let path = path.join(subdir.os_name());
+            Self::push_sections(path, &subdir, config, depth - 1, sections);
+        }
+    }
+    // Filtered and sorted entries of `dir`, each directory followed by its own
+    // (already read) children.
+    fn flatten_entries(dir: &FileSystemEntry, config: &Config) -> Vec<FileSystemEntry> {
+        let processor =
+            DataProcessor::new(dir.get_dir_entries().unwrap_or_default(), config.clone());
+
+        let mut flat = Vec::new();
+        for fse in processor.filter().sort().into_entries() {
+            let children = if fse.is_dir() {
+                Self::flatten_entries(&fse, config)
+            } else {
+                vec![]
+            };
+            flat.push(fse);
+            flat.extend(children);
+        }
+        flat
+    }
+    /// Writes the listing to stdout. A failing write is recorded as a serious
+    /// problem.
     pub fn print(&self) {
-        println!("{}", self.formatter.format())
+        let mut stdout = io::stdout().lock();
+        let res = self
+            .formatter
+            .write(&mut stdout)
+            .and_then(|_| stdout.flush());
+        // a closed pipe (e.g. `fls | head`) is not worth reporting
+        if let Err(e) = res
+            && e.kind() != io::ErrorKind::BrokenPipe
+        {
+            self.diagnostics.record(Diagnostic::write_error(e));
+        }
     }
 }
@@ -0,0 +1,141 @@
+use std::{
+    fmt,
+    io::{self, Write},
+    path::Path,
+    sync::Mutex,
+};
+
+use crate::error::LsError;
+
+/// How bad a problem is, following the exit statuses of GNU ls.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    // e.g. a subdirectory or an entry that cannot be read
+    Minor,
+    // e.g. a command line operand that cannot be accessed
+    Serious,
+}
+
+impl Severity {
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            Severity::Minor => 1,
+            Severity::Serious => 2,
+        }
+    }
+}
+
+/// What fls was doing when a problem occurred.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operation {
+    Access,
+    OpenDirectory,
+    ReadDirectory,
+    // writing the listing itself
+    Write,
+}
+
+impl Operation {
+    pub fn describe(&self) -> &'static str {
+        match self {
+            Operation::Access => "cannot access",
+            Operation::OpenDirectory => "cannot open directory",
+            Operation::ReadDirectory => "reading directory",
+            Operation::Write => "write error",
+        }
+    }
+}
+
+/// One problem met while listing, e.g. a permission denied or vanished entry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub operation: Operation,
+    // empty for a write error
+    pub path: String,
+    pub message: String,
+    pub severity: Severity,
+}
+
+impl Diagnostic {
+    pub fn new<P: AsRef<Path>, E: Into<LsError>>(
+        operation: Operation,
+        path: P,
+        error: E,
+        severity: Severity,
+    ) -> Self {
+        Self {
+            operation,
+            path: path.as_ref().display().to_string(),
+            message: error.into().message(),
+            severity,
+        }
+    }
+    /// A failure to write the listing, serious like in GNU ls.
+    pub fn write_error(error: io::Error) -> Self {
+        Self {
+            operation: Operation::Write,
+            path: String::new(),
+            message: LsError::from(error).message(),
+            severity: Severity::Serious,
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.operation == Operation::Write {
+            return write!(f, "{}: {}", self.operation.describe(), self.message);
+        }
+        write!(
+            f,
+            "{} '{}': {}",
+            self.operation.describe(),
+            self.path,
+            self.message
+        )
+    }
+}
+
+/// Collects the problems of a run, so listing can go on and the exit status
+/// can still tell about them. A default collector prints nothing, the caller
+/// decides what to do with [`Diagnostics::diagnostics`].
+#[derive(Debug, Default)]
+pub struct Diagnostics {
+    reported: Mutex<Vec<Diagnostic>>,
+    // print every problem to stderr as it is recorded
+    print: bool,
+}
+
+impl Diagnostics {
+    /// Collector that also prints every problem as `fls: <problem>` to stderr
+    /// when it is recorded, like GNU ls, after the listing written so far.
+    pub fn printing() -> Self {
+        Self {
+            print: true,
+            ..Self::default()
+        }
+    }
+    pub fn record(&self, diagnostic: Diagnostic) {
+        if self.print {
+            // nothing to do about a failing stdout here, it is reported on its own
+            let _ = io::stdout().flush();
+            eprintln!("fls: {diagnostic}");
+        }
+        self.lock().push(diagnostic);
+    }
+    /// Problems recorded so far, in the order they occurred.
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        self.lock().clone()
+    }
+    /// 0 if nothing went wrong, 1 for minor and 2 for serious problems.
+    pub fn exit_code(&self) -> u8 {
+        self.lock()
+            .iter()
+            .map(|d| d.severity.exit_code())
+            .max()
+            .unwrap_or(0)
+    }
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<Diagnostic>> {
+        self.reported.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
@@ -1,19 +1,156 @@
-use std::io;
+use std::{
+    fmt, io,
+    path::{Path, PathBuf},
+};
+
+use crate::json::JsonWriter;
 
 #[derive(Debug, thiserror::Error)]
 pub enum LsError {
     #[error("IOError {0}")]
     IOError(#[from] io::Error),
+    #[error("cannot {operation} '{}': {}", path.display(), strip_os_error(source))]
+    Io {
+        path: PathBuf,
+        operation: FsOperation,
+        kind: ErrorKind,
+        #[source]
+        source: io::Error,
+    },
     #[error("Trying to unwrap None value {0}")]
     NoneValue(String),
     #[error("Function cannot determine the type of file {0}")]
     UnknownTypeOfFile(String),
 }
 
+/// File system call that failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FsOperation {
+    Stat,
+    ReadDir,
+    ReadLink,
+    Canonicalize,
+}
+
+impl FsOperation {
+    pub fn name(&self) -> &'static str {
+        match self {
+            FsOperation::Stat => "stat",
+            FsOperation::ReadDir => "readdir",
+            FsOperation::ReadLink => "readlink",
+            FsOperation::Canonicalize => "canonicalize",
+        }
+    }
+}
+
+impl fmt::Display for FsOperation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Classification of an error, for callers that react to some of them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    Permission,
+    NotFound,
+    // too many levels of symbolic links
+    Loop,
+    Other,
+}
+
+impl ErrorKind {
+    pub fn from_io(error: &io::Error) -> Self {
+        #[cfg(unix)]
+        if error.raw_os_error() == Some(libc::ELOOP) {
+            return ErrorKind::Loop;
+        }
+        match error.kind() {
+            io::ErrorKind::PermissionDenied => ErrorKind::Permission,
+            io::ErrorKind::NotFound => ErrorKind::NotFound,
+            _ => ErrorKind::Other,
+        }
+    }
+    pub fn name(&self) -> &'static str {
+        match self {
+            ErrorKind::Permission => "permission",
+            ErrorKind::NotFound => "not_found",
+            ErrorKind::Loop => "loop",
+            ErrorKind::Other => "other",
+        }
+    }
+}
+
 impl LsError {
     pub fn none_from<S: AsRef<str>>(s: S) -> Self {
         LsError::NoneValue(s.as_ref().to_string())
     }
+    /// Error of `operation` on `path`.
+    pub fn io<P: AsRef<Path>>(operation: FsOperation, path: P, source: io::Error) -> Self {
+        LsError::Io {
+            path: path.as_ref().to_path_buf(),
+            operation,
+            kind: ErrorKind::from_io(&source),
+            source,
+        }
+    }
+    /// Attaches `operation` and `path` to an IO error, for `map_err`.
+    pub fn at<P: AsRef<Path>>(operation: FsOperation, path: P) -> impl FnOnce(io::Error) -> Self {
+        move |source| Self::io(operation, path, source)
+    }
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            LsError::IOError(e) => ErrorKind::from_io(e),
+            LsError::Io { kind, .. } => *kind,
+            LsError::NoneValue(_) | LsError::UnknownTypeOfFile(_) => ErrorKind::Other,
+        }
+    }
+    pub fn path(&self) -> Option<&Path> {
+        match self {
+            LsError::Io { path, .. } => Some(path),
+            _ => None,
+        }
+    }
+    pub fn operation(&self) -> Option<FsOperation> {
+        match self {
+            LsError::Io { operation, .. } => Some(*operation),
+            _ => None,
+        }
+    }
+    /// The cause alone, e.g. `Permission denied`, without path and operation.
+    pub fn message(&self) -> String {
+        match self {
+            LsError::IOError(e) | LsError::Io { source: e, .. } => strip_os_error(e),
+            e => e.to_string(),
+        }
+    }
+    /// Writes the error as an object with `kind`, `operation`, `path` and `message`.
+    pub fn write_json<W: io::Write>(&self, w: &mut JsonWriter<W>) -> io::Result<()> {
+        w.begin_object()?;
+        w.field_str("kind", self.kind().name())?;
+        w.field_opt_str("operation", self.operation().map(|op| op.name()))?;
+        w.field_opt_str(
+            "path",
+            self.path().map(|p| p.display().to_string()).as_deref(),
+        )?;
+        w.field_str("message", &self.message())?;
+        w.end_object()
+    }
+    pub fn to_json(&self) -> String {
+        let mut writer = JsonWriter::new(Vec::new(), false);
+        // writing into a `Vec` cannot fail
+        let _ = self.write_json(&mut writer);
+        String::from_utf8_lossy(&writer.into_inner()).into_owned()
+    }
+}
+
+// `Permission denied (os error 13)` -> `Permission denied`
+fn strip_os_error(error: &io::Error) -> String {
+    let message = error.to_string();
+    match message.find(" (os error") {
+        Some(end) => message[..end].to_string(),
+        None => message,
+    }
 }
 
 pub type LsResult<T> = Result<T, LsError>;
@@ -1,14 +1,26 @@
 use std::{
+    borrow::Cow,
     collections::HashSet,
     env,
+    ffi::{OsStr, OsString},
     fs::{self, DirEntry, Metadata},
     path::{Path, PathBuf},
 };
 
+use crate::diagnostics::{Diagnostic, Diagnostics, Operation, Severity};
+use crate::git::{self, GitStatus};
+use crate::gitignore::IgnoreFiles;
+use crate::ls_colors::{Indicator, LsColors};
+use crate::quoting::Quoting;
+use crate::sort::Order;
+use crate::theme::Theme;
 use crate::time::Time;
+#[cfg(unix)]
+use crate::users;
+use crate::width;
 use crate::{
     command::{Config, RecursionOptions},
-    error::{LsError, LsResult},
+    error::{FsOperation, LsError, LsResult},
 };
 
 #[derive(Debug, Clone, PartialEq, Eq, Default)]
@@ -21,21 +33,85 @@ pub enum FileColor {
     Blue,
     // for executable
     Green,
+    // for fifos and block/character devices
+    Yellow,
+    // for sockets
+    Magenta,
     // default,
     #[default]
     White,
-    // TODO: for files by file extension (white by default)
-    Other,
+    // SGR parameters taken from LS_COLORS (e.g. "01;34")
+    Other(String),
 }
 
 impl FileColor {
-    fn get_code(&self) -> &str {
+    fn get_code(&self) -> String {
+        if let Some(style) = self.theme_key().and_then(|key| Theme::global()?.get(key)) {
+            return format!("\x1b[{}m", style.sgr());
+        }
         match self {
-            FileColor::Red => "\x1b[31m",
-            FileColor::Green => "\x1b[32m",
-            FileColor::Blue => "\x1b[34m",
-            FileColor::Aqua => "\x1b[36m",
-            FileColor::Other | FileColor::White => "\x1b[37m",
+            FileColor::Red => "\x1b[31m".to_string(),
+            FileColor::Green => "\x1b[32m".to_string(),
+            FileColor::Yellow => "\x1b[33m".to_string(),
+            FileColor::Magenta => "\x1b[35m".to_string(),
+            FileColor::Blue => "\x1b[34m".to_string(),
+            FileColor::Aqua => "\x1b[36m".to_string(),
+            FileColor::White => "\x1b[37m".to_string(),
+            FileColor::Other(code) => format!("\x1b[{code}m"),
+        }
+    }
+    fn theme_key(&self) -> Option<&'static str> {
+        match self {
+            FileColor::Red => Some("broken_link"),
+            FileColor::Aqua => Some("link"),
+            FileColor::Blue => Some("directory"),
+            FileColor::Green => Some("executable"),
+            FileColor::Yellow => Some("special"),
+            FileColor::Magenta => Some("socket"),
+            FileColor::White => Some("file"),
+            FileColor::Other(_) => None,
+        }
+    }
+    /// Colour configured by `LS_COLORS`, see [`LsColors::pick`].
+    fn from_ls_colors(specific: &[Indicator], name: Option<&str>, base: Indicator) -> Option<Self> {
+        LsColors::global()?
+            .pick(specific, name, base)
+            .map(|code| FileColor::Other(code.to_string()))
+    }
+    /// Picks the colour of an entry whose built-in colour is `default`: a user
+    /// [`Theme`] wins over `LS_COLORS`, which wins over the built-in colours.
+    fn pick(
+        default: FileColor,
+        specific: &[Indicator],
+        name: Option<&str>,
+        base: Indicator,
+    ) -> Self {
+        if let Some(theme) = Theme::global() {
+            let themed = |color: &FileColor| color.theme_key().and_then(|k| theme.get(k)).is_some();
+
+            // type colours (executable, directory, ...) beat extension colours
+            if default != FileColor::White && themed(&default) {
+                return default;
+            }
+            if let Some(style) = name.and_then(|name| theme.extension(name)) {
+                return FileColor::Other(style.sgr());
+            }
+            if themed(&default) {
+                return default;
+            }
+        }
+        Self::from_ls_colors(specific, name, base).unwrap_or(default)
+    }
+    pub fn name(&self) -> &str {
+        match self {
+            FileColor::Red => "red",
+            FileColor::Green => "green",
+            FileColor::Yellow => "yellow",
+            FileColor::Magenta => "magenta",
+            FileColor::Blue => "blue",
+            FileColor::Aqua => "aqua",
+            FileColor::White => "white",
+            FileColor::Other(code) => code,
         }
     }
     fn reset(&self) -> &str {
@@ -58,8 +134,22 @@ pub struct MetaData {
     pub mode_str: String,
     pub executable: bool,
 
+    pub uid: u32,
+    pub gid: u32,
+
+    pub nlink: u64,
+    // allocated 512-byte blocks and the preferred I/O block size
+    pub blocks: u64,
+    pub blksize: u64,
+
+    // device id of block and character devices
+    pub rdev: u64,
+
     pub created_at: Time,
     pub modified_at: Time,
+    pub accessed_at: Time,
+    // last status change
+    pub changed_at: Time,
 }
 
 #[cfg(windows)]
@@ -74,28 +164,81 @@ pub struct MetaData {
 
     pub created_at: Time,
     pub modified_at: Time,
+    pub accessed_at: Time,
 }
 
 impl MetaData {
+    /// Major and minor number of a block or character device.
+    #[cfg(unix)]
+    pub fn device_numbers(&self) -> (u32, u32) {
+        let rdev = self.rdev as libc::dev_t;
+        (libc::major(rdev) as u32, libc::minor(rdev) as u32)
+    }
+    /// Bytes actually allocated on disk; smaller than `size` for sparse files.
+    #[cfg(unix)]
+    pub fn allocated_size(&self) -> u64 {
+        self.blocks * 512
+    }
+    #[cfg(windows)]
+    pub fn allocated_size(&self) -> u64 {
+        self.size
+    }
+    /// Allocated size like `ls -s` prints it: 1024-byte blocks, rounded up,
+    /// or a human readable size.
+    pub fn allocated_string(&self, human_size: bool) -> String {
+        allocated_string(self.allocated_size(), human_size)
+    }
+    /// Owner name, or the uid if it is unknown or `numeric` is set.
+    #[cfg(unix)]
+    pub fn owner(&self, numeric: bool) -> String {
+        if numeric {
+            self.uid.to_string()
+        } else {
+            users::user_name(self.uid).unwrap_or_else(|| self.uid.to_string())
+        }
+    }
+    /// Group name, or the gid if it is unknown or `numeric` is set.
+    #[cfg(unix)]
+    pub fn group(&self, numeric: bool) -> String {
+        if numeric {
+            self.gid.to_string()
+        } else {
+            users::group_name(self.gid).unwrap_or_else(|| self.gid.to_string())
+        }
+    }
     #[cfg(unix)]
     pub fn try_from(metadata: &Metadata) -> LsResult<Self> {
         use std::os::unix::fs::{MetadataExt, PermissionsExt};
+        use std::time::{Duration, UNIX_EPOCH};
 
         Ok(MetaData {
             size: metadata.len(),
             human_size: get_human_readable_size(metadata.len()),
             inode: metadata.ino(),
             mode: metadata.mode(),
-            mode_str: get_file_mode_formated(&metadata),
+            mode_str: get_file_mode_formated(metadata),
             executable: metadata.is_file() && metadata.permissions().mode() & 0o111 != 0,
-            created_at: Time::from(metadata.created()?),
+            uid: metadata.uid(),
+            gid: metadata.gid(),
+            nlink: metadata.nlink(),
+            blocks: metadata.blocks(),
+            blksize: metadata.blksize(),
+            rdev: metadata.rdev(),
+            // not every file system records a birth time (e.g. devtmpfs)
+            created_at: Time::from(metadata.created().or_else(|_| metadata.modified())?),
             modified_at: Time::from(metadata.modified()?),
+            accessed_at: Time::from(metadata.accessed().or_else(|_| metadata.modified())?),
+            changed_at: Time::from(
+                UNIX_EPOCH
+                    + Duration::new(
+                        u64::try_from(metadata.ctime()).unwrap_or(0),
+                        metadata.ctime_nsec() as u32,
+                    ),
+            ),
         })
     }
     #[cfg(windows)]
     pub fn try_from(metadata: &Metadata) -> LsResult<Self> {
-        use crate::time::Time;
-
         Ok(MetaData {
             size: metadata.len(),
             human_size: get_human_readable_size(metadata.len()),
@@ -105,10 +248,53 @@ impl MetaData {
 
             created_at: Time::from(metadata.created()?),
             modified_at: Time::from(metadata.modified()?),
+            accessed_at: Time::from(metadata.accessed()?),
         })
     }
 }
 
+/// Widths of the padded columns of a long listing, shared by all of its rows.
+#[derive(Debug, Clone, Default)]
+pub struct LongWidths {
+    pub nlink: usize,
+    pub size: usize,
+    pub time: usize,
+    pub owner: usize,
+    pub group: usize,
+    // one character `--git` status column
+    pub git: bool,
+}
+
+impl LongWidths {
+    pub fn new(entries: &[FileSystemEntry], config: &Config) -> Self {
+        fn widest(entries: &[FileSystemEntry], width: impl Fn(&FileSystemEntry) -> usize) -> usize {
+            entries.iter().map(width).max().unwrap_or(0)
+        }
+
+        Self {
+            #[cfg(unix)]
+            nlink: widest(entries, |fse| fse.metadata().nlink.to_string().len()),
+            #[cfg(windows)]
+            nlink: 0,
+            size: widest(entries, |fse| fse.size_string(config.humanable).len()),
+            time: widest(entries, |fse| fse.metadata().modified_at.format().len()),
+            #[cfg(unix)]
+            owner: widest(entries, |fse| {
+                width::display_width(&fse.metadata().owner(config.numeric_ids))
+            }),
+            #[cfg(unix)]
+            group: widest(entries, |fse| {
+                width::display_width(&fse.metadata().group(config.numeric_ids))
+            }),
+            #[cfg(windows)]
+            owner: 0,
+            #[cfg(windows)]
+            group: 0,
+            git: config.git,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Default)]
 pub struct FileStyle {
     pub suffix: Option<char>,
@@ -117,7 +303,8 @@ pub struct FileStyle {
 
 #[derive(Debug, Clone)]
 pub struct BaseInfo {
-    pub name: String,
+    // not necessarily valid unicode, see `Quoting` for how it is printed
+    pub name: OsString,
     pub style: FileStyle,
 
     pub path: PathBuf,
@@ -127,6 +314,7 @@ pub enum FileType {
     File,
     Directory,
     Link,
+    Special,
 }
 
 impl From<&FileSystemEntry> for FileType {
@@ -135,6 +323,7 @@ impl From<&FileSystemEntry> for FileType {
             FileSystemEntry::File { .. } => FileType::File,
             FileSystemEntry::Directory { .. } => FileType::Directory,
             FileSystemEntry::Link { .. } => FileType::Link,
+            FileSystemEntry::Special { .. } => FileType::Special,
         }
     }
 }
@@ -149,6 +338,9 @@ impl FileType {
     pub fn is_link(&self) -> bool {
         matches!(self, FileType::Link)
     }
+    pub fn is_special(&self) -> bool {
+        matches!(self, FileType::Special)
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -167,7 +359,77 @@ pub enum FileSystemEntry {
         base_info: BaseInfo,
         metadata: MetaData,
         target: PathBuf,
+        // what the link points to, `None` if the target does not exist
+        resolved: Option<Box<FileSystemEntry>>,
     },
+    Special {
+        base_info: BaseInfo,
+        metadata: MetaData,
+        kind: SpecialKind,
+    },
+}
+
+/// Kind of a file that is neither a regular file, a directory nor a link.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpecialKind {
+    Socket,
+    Fifo,
+    BlockDevice,
+    CharDevice,
+}
+
+impl SpecialKind {
+    #[cfg(unix)]
+    fn from_file_type(ft: fs::FileType) -> Option<Self> {
+        use std::os::unix::fs::FileTypeExt;
+
+        if ft.is_socket() {
+            Some(SpecialKind::Socket)
+        } else if ft.is_fifo() {
+            Some(SpecialKind::Fifo)
+        } else if ft.is_block_device() {
+            Some(SpecialKind::BlockDevice)
+        } else if ft.is_char_device() {
+            Some(SpecialKind::CharDevice)
+        } else {
+            None
+        }
+    }
+    #[cfg(not(unix))]
+    fn from_file_type(_ft: fs::FileType) -> Option<Self> {
+        None
+    }
+    pub fn name(&self) -> &'static str {
+        match self {
+            SpecialKind::Socket => "socket",
+            SpecialKind::Fifo => "fifo",
+            SpecialKind::BlockDevice => "block_device",
+            SpecialKind::CharDevice => "char_device",
+        }
+    }
+    pub fn mode_char(&self) -> char {
+        match self {
+            SpecialKind::Socket => 's',
+            SpecialKind::Fifo => 'p',
+            SpecialKind::BlockDevice => 'b',
+            SpecialKind::CharDevice => 'c',
+        }
+    }
+    pub fn is_device(&self) -> bool {
+        matches!(self, SpecialKind::BlockDevice | SpecialKind::CharDevice)
+    }
+    fn style(&self) -> FileStyle {
+        let (suffix, color, indicator) = match self {
+            SpecialKind::Socket => (Some('='), FileColor::Magenta, Indicator::Socket),
+            SpecialKind::Fifo => (Some('|'), FileColor::Yellow, Indicator::Fifo),
+            SpecialKind::BlockDevice => (None, FileColor::Yellow, Indicator::BlockDevice),
+            SpecialKind::CharDevice => (None, FileColor::Yellow, Indicator::CharDevice),
+        };
+        FileStyle {
+            suffix,
+            color: FileColor::pick(color, &[], None, indicator),
+        }
+    }
 }
 
 #[cfg(unix)]
@@ -183,7 +445,7 @@ fn get_file_mode_formated(md: &Metadata) -> String {
         ft if ft.is_dir() => 'd',
         ft if ft.is_file() => '-',
         ft if ft.is_symlink() => 'l',
-        _ => '?',
+        ft => SpecialKind::from_file_type(ft).map_or('?', |kind| kind.mode_char()),
     });
 
     // User permissions
@@ -247,14 +509,68 @@ fn get_file_mode_formated(md: &Metadata) -> String {
     mode
 }
 
-fn path_to_string<P: AsRef<Path>>(path: P) -> LsResult<String> {
+// LS_COLORS indicators that take precedence over the plain `fi` colour
+#[cfg(unix)]
+fn file_indicators(md: &MetaData) -> Vec<Indicator> {
+    let mut indicators = Vec::new();
+    if md.mode & 0o4000 != 0 {
+        indicators.push(Indicator::Setuid);
+    }
+    if md.mode & 0o2000 != 0 {
+        indicators.push(Indicator::Setgid);
+    }
+    if md.executable {
+        indicators.push(Indicator::Executable);
+    }
+    indicators
+}
+#[cfg(windows)]
+fn file_indicators(_md: &MetaData) -> Vec<Indicator> {
+    vec![]
+}
+
+// LS_COLORS indicators that take precedence over the plain `di` colour
+#[cfg(unix)]
+fn dir_indicators(md: &MetaData) -> Vec<Indicator> {
+    let sticky = md.mode & 0o1000 != 0;
+    let other_writable = md.mode & 0o002 != 0;
+
+    match (sticky, other_writable) {
+        (true, true) => vec![Indicator::StickyOtherWritable, Indicator::OtherWritable],
+        (false, true) => vec![Indicator::OtherWritable],
+        (true, false) => vec![Indicator::Sticky],
+        (false, false) => vec![],
+    }
+}
+#[cfg(windows)]
+fn dir_indicators(_md: &MetaData) -> Vec<Indicator> {
+    vec![]
+}
+
+fn path_name<P: AsRef<Path>>(path: P) -> LsResult<OsString> {
     Ok(path
         .as_ref()
         .file_name()
         .ok_or(LsError::none_from("incorrect file_name"))?
-        .to_str()
-        .ok_or(LsError::none_from("non-valid unicode in name"))?
-        .to_string())
+        .to_os_string())
+}
+
+/// Formats a number of allocated bytes the way `ls -s` and its `total` line do.
+pub fn allocated_string(bytes: u64, human_size: bool) -> String {
+    if human_size {
+        get_human_readable_size(bytes)
+    } else {
+        bytes.div_ceil(1024).to_string()
+    }
+}
+
+/// Formats a size in bytes like the size column of the long format.
+pub fn size_string(bytes: u64, human_size: bool) -> String {
+    if human_size {
+        get_human_readable_size(bytes)
+    } else {
+        bytes.to_string()
+    }
 }
 
 fn get_human_readable_size(size: u64) -> String {
@@ -284,56 +600,203 @@ fn get_human_readable_size(size: u64) -> String {
     }
 }
 
+// Records a problem with the directory at `depth` of a walk: the directory of an
+// operand (depth 0) is serious trouble, anything below it a minor problem.
+fn record_problem<E: Into<LsError>>(
+    walk: Walk<'_>,
+    operation: Operation,
+    path: &Path,
+    error: E,
+    depth: usize,
+) {
+    let severity = if depth == 0 {
+        Severity::Serious
+    } else {
+        Severity::Minor
+    };
+    if let Some(diagnostics) = walk.diagnostics {
+        diagnostics.record(Diagnostic::new(operation, path, error, severity));
+    }
+}
+
+// Entries of the directory at `path` (at `depth` of the walk). Entries that
+// cannot be read are recorded and skipped, as is the whole directory if it
+// cannot be opened. Entries `ignore` rejects are skipped before being read.
+// With an order the directory is read in full and sorted, otherwise entries
+// are read one at a time.
+fn read_dir_entries<'a>(
+    path: &Path,
+    depth: usize,
+    walk: Walk<'a>,
+    ignore: Option<&'a IgnoreFiles>,
+) -> Option<Box<dyn Iterator<Item = FileSystemEntry> + 'a>> {
+    let dir = fs::read_dir(path)
+        .map_err(LsError::at(FsOperation::ReadDir, path))
+        .map_err(|e| record_problem(walk, Operation::OpenDirectory, path, e, depth))
+        .ok()?;
+    let path = path.to_path_buf();
+
+    let entries = dir.filter_map(move |entry| {
+        let entry = entry
+            .map_err(LsError::at(FsOperation::ReadDir, &path))
+            .map_err(|e| record_problem(walk, Operation::ReadDirectory, &path, e, depth + 1))
+            .ok()?;
+        let entry_path = entry.path();
+        if let Some(ignore) = ignore {
+            let is_dir = entry.file_type().is_ok_and(|t| t.is_dir());
+            if ignore.is_ignored(&entry_path, is_dir) {
+                return None;
+            }
+        }
+        FileSystemEntry::from_dir_entry_with(entry, walk.dereference)
+            .map_err(|e| record_problem(walk, Operation::Access, &entry_path, e, depth + 1))
+            .ok()
+    });
+    match walk.order {
+        Some(order) => {
+            let mut entries: Vec<_> = entries.collect();
+            order.apply(&mut entries);
+            Some(Box::new(entries.into_iter()))
+        }
+        None => Some(Box::new(entries)),
+    }
+}
+
+/// Options of one directory walk, see [`FileSystemEntry::walk_start_dir`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Walk<'a> {
+    // keep the entries in the tree after reporting them
+    pub retain: bool,
+    // replace links by the entries they point to
+    pub dereference: bool,
+    // skip what `.gitignore`, `.ignore` and `.fdignore` files exclude
+    pub gitignore: bool,
+    // report the entries of every directory in this order instead of as read
+    pub order: Option<Order<'a>>,
+    // where problems are recorded, they are dropped without one
+    pub diagnostics: Option<&'a Diagnostics>,
+}
+
+impl<'a> Walk<'a> {
+    /// Walk reading the tree as `config` asks, recording problems in `diagnostics`.
+    pub fn new(config: &Config, retain: bool, diagnostics: &'a Diagnostics) -> Self {
+        Self {
+            retain,
+            dereference: config.dereference,
+            gitignore: config.gitignore,
+            order: None,
+            diagnostics: Some(diagnostics),
+        }
+    }
+}
+
 impl FileSystemEntry {
+    /// Like [`FileSystemEntry::new_for_path`] for the first path of `config`,
+    /// without reporting the problems met below it.
     pub fn new_with_config(config: &Config) -> LsResult<Self> {
-        let path = if config.path.eq(".") {
+        Self::new_for_path(&config.paths[0], config, &Diagnostics::default())
+    }
+    /// Builds the entry of the operand `path` and reads its contents as `config`
+    /// asks. Directories that cannot be read are recorded in `diagnostics`.
+    pub fn new_for_path(path: &str, config: &Config, diagnostics: &Diagnostics) -> LsResult<Self> {
+        let mut fse = Self::root_for_path(path, config)?;
+        fse.walk_start_dir(
+            Self::recursion_depth(config),
+            Walk::new(config, true, diagnostics),
+            &mut |_, _| Ok(()),
+        )?;
+        Ok(fse)
+    }
+    /// Builds the start entry described by `config` without reading its contents.
+    pub fn root_with_config(config: &Config) -> LsResult<Self> {
+        Self::root_for_path(&config.paths[0], config)
+    }
+    /// Builds the entry of the operand `path` without reading its contents.
+    ///
+    /// Like GNU ls, links to directories given on the command line are followed
+    /// unless they are listed in long format. Directories are named after their
+    /// last component, any other operand is named exactly as it was given.
+    pub fn root_for_path(path: &str, config: &Config) -> LsResult<Self> {
+        let operand = path;
+        let path = if operand.eq(".") {
             env::current_dir()?
         } else {
-            PathBuf::from(&config.path)
+            PathBuf::from(operand)
         };
-        let name = path_to_string(&path)?;
-        let metadata = fs::symlink_metadata(&path)?;
-        let mut fse = Self::new_from_values(name, path, metadata)?;
 
-        fse.fill_start_dir(config.recursive.clone().map(|r| match r {
+        let mut metadata =
+            fs::symlink_metadata(&path).map_err(LsError::at(FsOperation::Stat, &path))?;
+        if metadata.is_symlink()
+            && let Ok(target) = fs::metadata(&path)
+            && (config.dereference || (target.is_dir() && !config.long))
+        {
+            metadata = target;
+        }
+
+        let name = if metadata.is_dir() {
+            // `..` and `/` have no file name
+            path_name(&path).unwrap_or_else(|_| operand.into())
+        } else {
+            operand.into()
+        };
+        Self::new_from_values(name, path, metadata)
+    }
+    pub fn recursion_depth(config: &Config) -> Option<usize> {
+        config.recursive.clone().map(|r| match r {
             RecursionOptions::Depth(depth) => depth,
             RecursionOptions::Unlimited => 40,
             RecursionOptions::No => 0,
-        }))?;
-        Ok(fse)
+        })
     }
-    pub fn new_from_values(name: String, path: PathBuf, metadata: Metadata) -> LsResult<Self> {
+    pub fn new_from_values(name: OsString, path: PathBuf, metadata: Metadata) -> LsResult<Self> {
         let meta_data = MetaData::try_from(&metadata)?;
 
         if metadata.is_file() {
+            #[cfg(unix)]
+            let default_color = if meta_data.executable {
+                FileColor::Green
+            } else {
+                FileColor::default()
+            };
+            #[cfg(windows)]
+            let default_color = FileColor::default();
+
+            let extension = path
+                .extension()
+                .and_then(|s| s.to_str().map(|s| s.to_string()));
+            let color = FileColor::pick(
+                default_color,
+                &file_indicators(&meta_data),
+                Some(&name.to_string_lossy()),
+                Indicator::File,
+            );
+
             Ok(FileSystemEntry::File {
-                extension: path
-                    .extension()
-                    .and_then(|s| s.to_str().map(|s| s.to_string())),
+                extension,
                 base_info: BaseInfo {
                     name,
-                    #[cfg(windows)]
-                    style: FileStyle::default(),
-                    #[cfg(unix)]
-                    style: if meta_data.executable {
-                        FileStyle {
-                            suffix: None,
-                            color: FileColor::Green,
-                        }
-                    } else {
-                        FileStyle::default()
+                    style: FileStyle {
+                        suffix: None,
+                        color,
                     },
                     path,
                 },
                 metadata: meta_data,
             })
         } else if metadata.is_dir() {
+            let color = FileColor::pick(
+                FileColor::Blue,
+                &dir_indicators(&meta_data),
+                None,
+                Indicator::Directory,
+            );
+
             Ok(FileSystemEntry::Directory {
                 base_info: BaseInfo {
                     name,
                     style: FileStyle {
                         suffix: Some('/'),
-                        color: FileColor::Blue,
+                        color,
                     },
                     path,
                 },
@@ -341,86 +804,228 @@ impl FileSystemEntry {
                 entries: vec![],
             })
         } else if metadata.is_symlink() {
-            let target = fs::read_link(&path)?;
+            let target = fs::read_link(&path).map_err(LsError::at(FsOperation::ReadLink, &path))?;
+            let resolved = Self::resolve_link(&path);
+            let color = if resolved.is_some() {
+                FileColor::pick(FileColor::Aqua, &[], None, Indicator::Link)
+            } else {
+                FileColor::pick(FileColor::Red, &[Indicator::Orphan], None, Indicator::Link)
+            };
+
             Ok(FileSystemEntry::Link {
                 base_info: BaseInfo {
                     name,
                     style: FileStyle {
                         suffix: Some('@'),
-                        color: FileColor::Aqua,
+                        color,
                     },
                     path,
                 },
                 metadata: meta_data,
                 target,
+                resolved,
+            })
+        } else if let Some(kind) = SpecialKind::from_file_type(metadata.file_type()) {
+            Ok(FileSystemEntry::Special {
+                base_info: BaseInfo {
+                    name,
+                    style: kind.style(),
+                    path,
+                },
+                metadata: meta_data,
+                kind,
             })
         } else {
-            Err(LsError::UnknownTypeOfFile(name.clone()))
+            Err(LsError::UnknownTypeOfFile(
+                name.to_string_lossy().into_owned(),
+            ))
         }
     }
-    pub fn fill_start_dir(&mut self, recursive: Option<usize>) -> LsResult<()> {
+    // Entry of the final target of the link at `path`, at the end of the whole
+    // chain of links; `None` for dangling links.
+    fn resolve_link(path: &Path) -> Option<Box<Self>> {
+        let target_path = fs::canonicalize(path).ok()?;
+        let metadata = fs::metadata(&target_path).ok()?;
+        // `/` has no file name
+        let name = target_path
+            .file_name()
+            .unwrap_or(target_path.as_os_str())
+            .to_os_string();
+        Self::new_from_values(name, target_path, metadata)
+            .ok()
+            .map(Box::new)
+    }
+    /// Whether this is a link whose target does not exist.
+    pub fn is_broken_link(&self) -> bool {
+        matches!(self, FileSystemEntry::Link { resolved: None, .. })
+    }
+    pub fn fill_start_dir(
+        &mut self,
+        recursive: Option<usize>,
+        diagnostics: &Diagnostics,
+    ) -> LsResult<()> {
+        let walk = Walk {
+            retain: true,
+            diagnostics: Some(diagnostics),
+            ..Walk::default()
+        };
+        self.walk_start_dir(recursive, walk, &mut |_, _| Ok(()))
+    }
+    /// Reads the directory like [`FileSystemEntry::fill_start_dir`], reporting every
+    /// entry to `on_entry` together with its depth (direct children are depth 1)
+    /// as soon as it is discovered. Directories are reported before their contents.
+    ///
+    /// With `walk.retain` set to `false` the entries are dropped after being
+    /// reported, so arbitrarily large trees can be streamed in constant memory.
+    /// With `walk.gitignore` set, ignored entries are skipped and ignored
+    /// directories are not descended into.
+    pub fn walk_start_dir<F>(
+        &mut self,
+        recursive: Option<usize>,
+        walk: Walk<'_>,
+        on_entry: &mut F,
+    ) -> LsResult<()>
+    where
+        F: FnMut(&FileSystemEntry, usize) -> LsResult<()>,
+    {
+        let ignore = walk
+            .gitignore
+            .then(|| IgnoreFiles::for_root(&self.base_info().path));
         if let Some(depth) = recursive {
             let mut visited_paths = HashSet::new();
-            self.fill_dir_recursive_safe(depth, 0, &mut visited_paths);
+            self.fill_dir_recursive_safe(
+                depth,
+                0,
+                &mut visited_paths,
+                walk,
+                ignore.as_ref(),
+                on_entry,
+            )?;
         } else {
-            self.fill_dir_non_recursive()?;
+            self.fill_dir_non_recursive(walk, ignore.as_ref(), on_entry)?;
         }
         Ok(())
     }
-    fn fill_dir_recursive_safe(
+    fn fill_dir_recursive_safe<F>(
         &mut self,
         max_depth: usize,
         current_depth: usize,
         visited_paths: &mut HashSet<PathBuf>,
-    ) {
+        walk: Walk<'_>,
+        // rules in effect in the directory, before reading its own files
+        ignore: Option<&IgnoreFiles>,
+        on_entry: &mut F,
+    ) -> LsResult<()>
+    where
+        F: FnMut(&FileSystemEntry, usize) -> LsResult<()>,
+    {
         if let FileSystemEntry::Directory {
             base_info, entries, ..
         } = self
         {
             if current_depth >= max_depth {
-                return;
+                return Ok(());
             }
 
             let canonical_path = match base_info.path.canonicalize() {
                 Ok(path) => path,
-                Err(_) => return,
+                Err(e) => {
+                    let e = LsError::io(FsOperation::Canonicalize, &base_info.path, e);
+                    record_problem(
+                        walk,
+                        Operation::OpenDirectory,
+                        &base_info.path,
+                        e,
+                        current_depth,
+                    );
+                    return Ok(());
+                }
             };
 
             if visited_paths.contains(&canonical_path) {
-                return;
+                return Ok(());
             }
             visited_paths.insert(canonical_path.clone());
 
-            let dir_entries = match fs::read_dir(&base_info.path) {
-                Ok(entries) => entries,
-                Err(_) => return,
+            let ignore = ignore.map(|ignore| ignore.enter(&base_info.path));
+            let Some(dir_entries) =
+                read_dir_entries(&base_info.path, current_depth, walk, ignore.as_ref())
+            else {
+                visited_paths.remove(&canonical_path);
+                return Ok(());
             };
 
-            for entry in dir_entries.flatten() {
-                if let Ok(mut fse) = FileSystemEntry::from_dir_entry(entry) {
-                    if let FileSystemEntry::Directory { .. } = &mut fse {
-                        fse.fill_dir_recursive_safe(max_depth, current_depth + 1, visited_paths);
-                    }
+            for mut fse in dir_entries {
+                on_entry(&fse, current_depth + 1)?;
+                if let FileSystemEntry::Directory { .. } = &mut fse {
+                    fse.fill_dir_recursive_safe(
+                        max_depth,
+                        current_depth + 1,
+                        visited_paths,
+                        walk,
+                        ignore.as_ref(),
+                        on_entry,
+                    )?;
+                }
+                if walk.retain {
                     entries.push(fse);
                 }
             }
 
             visited_paths.remove(&canonical_path);
         }
+        Ok(())
     }
-    fn fill_dir_non_recursive(&mut self) -> LsResult<()> {
+    fn fill_dir_non_recursive<F>(
+        &mut self,
+        walk: Walk<'_>,
+        ignore: Option<&IgnoreFiles>,
+        on_entry: &mut F,
+    ) -> LsResult<()>
+    where
+        F: FnMut(&FileSystemEntry, usize) -> LsResult<()>,
+    {
         if let FileSystemEntry::Directory {
             base_info, entries, ..
         } = self
         {
-            for entry in fs::read_dir(&base_info.path)?.flatten() {
-                if let Ok(fse) = FileSystemEntry::from_dir_entry(entry) {
+            let ignore = ignore.map(|ignore| ignore.enter(&base_info.path));
+            for fse in read_dir_entries(&base_info.path, 0, walk, ignore.as_ref())
+                .into_iter()
+                .flatten()
+            {
+                on_entry(&fse, 1)?;
+                if walk.retain {
                     entries.push(fse)
                 }
             }
         }
         Ok(())
     }
+    /// Rearranges the entries of an already read tree, at every level.
+    pub fn arrange_entries<F>(&mut self, arrange: &mut F)
+    where
+        F: FnMut(&mut [FileSystemEntry]),
+    {
+        if let FileSystemEntry::Directory { entries, .. } = self {
+            arrange(entries);
+            for fse in entries {
+                fse.arrange_entries(arrange);
+            }
+        }
+    }
+    /// Drops the entries `keep` rejects from an already read tree, at every level.
+    pub fn retain_entries<F>(&mut self, keep: &mut F)
+    where
+        F: FnMut(&FileSystemEntry) -> bool,
+    {
+        if let FileSystemEntry::Directory { entries, .. } = self {
+            entries.retain(|fse| keep(fse));
+            for fse in entries {
+                fse.retain_entries(keep);
+            }
+        }
+    }
     pub fn get_dir_entries(&self) -> Option<Vec<FileSystemEntry>> {
         match self {
             FileSystemEntry::Directory { entries, .. } => Some(entries.clone()),
@@ -428,32 +1033,42 @@ impl FileSystemEntry {
         }
     }
     pub fn get_styled_name_by_info(&self, info: &BaseInfo) -> String {
-        let suffix = if let Some(suffix) = info.style.suffix {
-            suffix.to_string()
+        Self::style_info(info, true, Quoting::default())
+    }
+    // Quoted name of `info` with its classify suffix, wrapped in the colour
+    // escape codes only if `colored`.
+    fn style_info(info: &BaseInfo, colored: bool, quoting: Quoting) -> String {
+        let name = quoting.quote(&info.name);
+        let name = if colored {
+            info.style.color.wrap(name)
         } else {
-            String::new()
+            name
         };
-        format!("{}{}", info.style.color.wrap(&info.name), suffix)
+        match info.style.suffix {
+            Some(suffix) => format!("{name}{suffix}"),
+            None => name,
+        }
     }
     pub fn get_styled_name(&self) -> String {
-        match self {
-            FileSystemEntry::File { base_info, .. } => self.get_styled_name_by_info(base_info),
-            FileSystemEntry::Directory { base_info, .. } => self.get_styled_name_by_info(base_info),
-            FileSystemEntry::Link { base_info, .. } => self.get_styled_name_by_info(base_info),
-        }
+        self.styled_name(true)
+    }
+    /// Name with its classify suffix, wrapped in the colour escape codes only if `colored`.
+    pub fn styled_name(&self, colored: bool) -> String {
+        self.styled_name_quoted(colored, Quoting::default())
+    }
+    /// Like [`FileSystemEntry::styled_name`] with the name quoted by `quoting`.
+    pub fn styled_name_quoted(&self, colored: bool, quoting: Quoting) -> String {
+        Self::style_info(self.base_info(), colored, quoting)
     }
     pub fn is_hidden(&self) -> bool {
-        match self {
-            FileSystemEntry::File { base_info, .. } => base_info.name.starts_with("."),
-            FileSystemEntry::Directory { base_info, .. } => base_info.name.starts_with("."),
-            FileSystemEntry::Link { base_info, .. } => base_info.name.starts_with("."),
-        }
+        self.os_name().as_encoded_bytes().starts_with(b".")
     }
     pub fn metadata(&self) -> &MetaData {
         match self {
             FileSystemEntry::File { metadata, .. } => metadata,
             FileSystemEntry::Directory { metadata, .. } => metadata,
             FileSystemEntry::Link { metadata, .. } => metadata,
+            FileSystemEntry::Special { metadata, .. } => metadata,
         }
     }
     pub fn base_info(&self) -> &BaseInfo {
@@ -461,6 +1076,7 @@ impl FileSystemEntry {
             FileSystemEntry::File { base_info, .. } => base_info,
             FileSystemEntry::Directory { base_info, .. } => base_info,
             FileSystemEntry::Link { base_info, .. } => base_info,
+            FileSystemEntry::Special { base_info, .. } => base_info,
         }
     }
     pub fn from_path<S: AsRef<str>>(path: S) -> LsResult<Self> {
@@ -469,93 +1085,232 @@ impl FileSystemEntry {
         } else {
             PathBuf::from(path.as_ref())
         };
-        let metadata = fs::symlink_metadata(&path)?;
+        let metadata =
+            fs::symlink_metadata(&path).map_err(LsError::at(FsOperation::Stat, &path))?;
 
-        let name = path_to_string(&path)?;
+        let name = path_name(&path)?;
 
         FileSystemEntry::new_from_values(name, path, metadata)
     }
     pub fn from_dir_entry(entry: DirEntry) -> LsResult<Self> {
+        Self::from_dir_entry_with(entry, false)
+    }
+    /// Like [`FileSystemEntry::from_dir_entry`], but with `dereference` set a link
+    /// is listed with the metadata of its target (unless the link is broken).
+    pub fn from_dir_entry_with(entry: DirEntry, dereference: bool) -> LsResult<Self> {
         let path = entry.path();
-        let metadata = entry.metadata()?;
+        let metadata = entry
+            .metadata()
+            .map_err(LsError::at(FsOperation::Stat, &path))?;
+        let metadata = if dereference && metadata.is_symlink() {
+            fs::metadata(&path).unwrap_or(metadata)
+        } else {
+            metadata
+        };
 
-        let name = path_to_string(&path)?;
+        let name = path_name(&path)?;
         FileSystemEntry::new_from_values(name, path, metadata)
     }
-    pub fn name(&self) -> &str {
+    /// Name of the entry, with invalid unicode replaced by `U+FFFD`.
+    pub fn name(&self) -> Cow<'_, str> {
+        self.os_name().to_string_lossy()
+    }
+    pub fn os_name(&self) -> &OsStr {
         match self {
             FileSystemEntry::File { base_info, .. } => &base_info.name,
             FileSystemEntry::Directory { base_info, .. } => &base_info.name,
             FileSystemEntry::Link { base_info, .. } => &base_info.name,
+            FileSystemEntry::Special { base_info, .. } => &base_info.name,
         }
     }
     pub fn cname(&self) -> String {
-        match self {
-            FileSystemEntry::File { base_info, .. } => base_info.name.clone(),
-            FileSystemEntry::Directory { base_info, .. } => base_info.name.clone(),
-            FileSystemEntry::Link { base_info, .. } => base_info.name.clone(),
-        }
+        self.name().into_owned()
     }
     pub fn style(&self) -> &FileStyle {
         match self {
             FileSystemEntry::File { base_info, .. } => &base_info.style,
             FileSystemEntry::Directory { base_info, .. } => &base_info.style,
             FileSystemEntry::Link { base_info, .. } => &base_info.style,
+            FileSystemEntry::Special { base_info, .. } => &base_info.style,
         }
     }
-    pub fn to_string_short(&self) -> String {
-        self.get_styled_name()
+    pub fn to_string_short(&self, colored: bool, quoting: Quoting) -> String {
+        self.styled_name_quoted(colored, quoting)
     }
     pub fn is_dir(&self) -> bool {
         matches!(self, FileSystemEntry::Directory { .. })
     }
+    /// Status in the git repository the entry lies in, if any.
+    pub fn git_status(&self) -> Option<GitStatus> {
+        git::status(&self.base_info().path, self.is_dir())
+    }
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            FileSystemEntry::File { .. } => "file",
+            FileSystemEntry::Directory { .. } => "directory",
+            FileSystemEntry::Link { .. } => "link",
+            FileSystemEntry::Special { kind, .. } => kind.name(),
+        }
+    }
+    /// Name column of the long format: links are shown as `name -> target`, with
+    /// the target styled by its own type (or as missing if the link is broken).
+    pub fn long_name(&self, colored: bool) -> String {
+        self.long_name_quoted(colored, Quoting::default())
+    }
+    /// Like [`FileSystemEntry::long_name`] with names quoted by `quoting`.
+    pub fn long_name_quoted(&self, colored: bool, quoting: Quoting) -> String {
+        let FileSystemEntry::Link {
+            base_info,
+            target,
+            resolved,
+            ..
+        } = self
+        else {
+            return self.styled_name_quoted(colored, quoting);
+        };
+
+        let target = match resolved {
+            // the target as written, styled by the final target's type
+            Some(resolved) => Self::style_info(
+                &BaseInfo {
+                    name: target.as_os_str().to_os_string(),
+                    ..resolved.base_info().clone()
+                },
+                colored,
+                quoting,
+            ),
+            None if colored => FileColor::pick(
+                FileColor::Red,
+                &[Indicator::Missing],
+                None,
+                Indicator::Orphan,
+            )
+            .wrap(quoting.quote(target.as_os_str())),
+            None => quoting.quote(target.as_os_str()),
+        };
+        let name = quoting.quote(&base_info.name);
+        let name = if colored {
+            base_info.style.color.wrap(name)
+        } else {
+            name
+        };
+        format!("{name} -> {target}")
+    }
+    /// Size column of the long format: `major, minor` for devices, the
+    /// (human readable) size otherwise.
+    pub fn size_string(&self, human_size: bool) -> String {
+        let md = self.metadata();
+        #[cfg(unix)]
+        if let FileSystemEntry::Special { kind, .. } = self
+            && kind.is_device()
+        {
+            let (major, minor) = md.device_numbers();
+            return format!("{major}, {minor}");
+        }
+        if human_size {
+            md.human_size.to_string()
+        } else {
+            md.size.to_string()
+        }
+    }
+    // Mode, size and date columns of the long format, padded to their widths and
+    // painted by the user theme (if any) when `colored`.
+    fn long_columns(
+        &self,
+        human_size: bool,
+        widths: &LongWidths,
+        colored: bool,
+    ) -> (String, String, String) {
+        let md = self.metadata();
+        let size = self.size_string(human_size);
+        let date = md.modified_at.format();
+        // padding stays outside of the escape codes
+        let size_pad = " ".repeat(widths.size.saturating_sub(size.len()));
+        let date_pad = " ".repeat(widths.time.saturating_sub(date.len()));
+
+        match Theme::global() {
+            Some(theme) if colored => (
+                theme.paint_mode(&md.mode_str),
+                theme.paint_size(&size, md.size) + &size_pad,
+                date_pad + &theme.paint_date(&date, &md.modified_at),
+            ),
+            _ => (md.mode_str.clone(), size + &size_pad, date_pad + &date),
+        }
+    }
+    #[cfg(unix)]
+    fn owner_columns(&self, numeric: bool, widths: &LongWidths, colored: bool) -> (String, String) {
+        let md = self.metadata();
+        let owner = md.owner(numeric);
+        let group = md.group(numeric);
+        let owner_pad = " ".repeat(widths.owner.saturating_sub(width::display_width(&owner)));
+        let group_pad = " ".repeat(widths.group.saturating_sub(width::display_width(&group)));
+
+        match Theme::global() {
+            Some(theme) if colored => (
+                theme.paint("owner", &owner) + &owner_pad,
+                theme.paint("group", &group) + &group_pad,
+            ),
+            _ => (owner + &owner_pad, group + &group_pad),
+        }
+    }
+    // `--git` status code followed by a space, `-` outside of a repository.
+    fn git_column(&self, widths: &LongWidths, colored: bool) -> String {
+        if !widths.git {
+            return String::new();
+        }
+        let status = self.git_status();
+        let code = status.map_or('-', |status| status.code()).to_string();
+        let code = match (Theme::global(), status) {
+            (Some(theme), Some(status)) if colored => {
+                theme.paint(&format!("git.{}", status.name()), &code)
+            }
+            _ => code,
+        };
+        code + " "
+    }
     #[cfg(unix)]
     pub fn to_string_long(
         &self,
         human_size: bool,
         inode: bool,
-        max_size: usize,
-        max_time: usize,
+        numeric_ids: bool,
+        widths: &LongWidths,
+        colored: bool,
+        quoting: Quoting,
     ) -> String {
-        let styled_name = self.get_styled_name();
+        let styled_name = self.long_name_quoted(colored, quoting);
         let md = self.metadata();
-        let date_str = md.modified_at.format();
+        let (mode, size, date) = self.long_columns(human_size, widths, colored);
+        let (owner, group) = self.owner_columns(numeric_ids, widths, colored);
         format!(
-            "{}{} {:<size_width$} {:>time_width$} {}",
+            "{}{} {:>nlink_width$} {} {} {} {} {}{}",
             if inode {
                 format!("{} ", md.inode)
             } else {
                 String::new()
             },
-            md.mode_str,
-            if human_size {
-                md.human_size.to_string()
-            } else {
-                md.size.to_string()
-            },
-            date_str,
+            mode,
+            md.nlink,
+            owner,
+            group,
+            size,
+            date,
+            self.git_column(widths, colored),
             styled_name,
-            size_width = max_size,
-            time_width = max_time,
+            nlink_width = widths.nlink,
         )
     }
     #[cfg(windows)]
-    pub fn to_string_long(&self, human_size: bool, max_size: usize, max_time: usize) -> String {
-        let styled_name = self.get_styled_name();
-        let md = self.metadata();
-        let date_str = md.modified_at.format();
-        format!(
-            "{} {:<size_width$} {:>time_width$} {}",
-            md.mode_str,
-            if human_size {
-                md.human_size.to_string()
-            } else {
-                md.size.to_string()
-            },
-            date_str,
-            styled_name,
-            size_width = max_size,
-            time_width = max_time,
-        )
+    pub fn to_string_long(
+        &self,
+        human_size: bool,
+        widths: &LongWidths,
+        colored: bool,
+        quoting: Quoting,
+    ) -> String {
+        let styled_name = self.long_name_quoted(colored, quoting);
+        let (mode, size, date) = self.long_columns(human_size, widths, colored);
+        let git = self.git_column(widths, colored);
+        format!("{mode} {size} {date} {git}{styled_name}")
     }
 }
@@ -0,0 +1,1107 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs::{self, File},
+    io::{Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+    sync::{Mutex, OnceLock},
+    time::UNIX_EPOCH,
+};
+
+use crate::gitignore::IgnoreFiles;
+
+const HASH_LEN: usize = 20;
+// mode of a submodule in the index and in trees
+const GITLINK_MODE: u32 = 0o160000;
+const SYMLINK_MODE: u32 = 0o120000;
+// longest chain of deltas read, the most `git pack-objects --depth` allows
+const MAX_DELTA_DEPTH: usize = 4095;
+
+type ObjectId = [u8; HASH_LEN];
+
+/// Status of an entry in its git repository, as shown by `--git`. A directory
+/// gets the most severe status of what it contains.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum GitStatus {
+    Unmodified,
+    Ignored,
+    New,
+    Staged,
+    Modified,
+    Conflicted,
+}
+
+impl GitStatus {
+    pub fn name(&self) -> &'static str {
+        match self {
+            GitStatus::Unmodified => "unmodified",
+            GitStatus::Ignored => "ignored",
+            GitStatus::New => "new",
+            GitStatus::Staged => "staged",
+            GitStatus::Modified => "modified",
+            GitStatus::Conflicted => "conflicted",
+        }
+    }
+    /// One character column of the long format.
+    pub fn code(&self) -> char {
+        match self {
+            GitStatus::Unmodified => '-',
+            GitStatus::Ignored => 'I',
+            GitStatus::New => 'N',
+            GitStatus::Staged => 'S',
+            GitStatus::Modified => 'M',
+            GitStatus::Conflicted => 'C',
+        }
+    }
+}
+
+/// Status of `path` in the repository around it, `None` outside of a
+/// repository or if the repository cannot be read. Repositories with SHA-256
+/// object names are not supported and get `None` too. Every repository is
+/// read once per run, on its first lookup.
+pub fn status(path: &Path, is_dir: bool) -> Option<GitStatus> {
+    static CACHE: OnceLock<Mutex<Cache>> = OnceLock::new();
+    let mut cache = CACHE
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap_or_else(|e| e.into_inner());
+
+    let path = absolute(path)?;
+    // a repository the entry is part of, or for the top of a work tree the
+    // repository it holds
+    let (workdir, relative) = match path.parent().and_then(|dir| cache.workdir(dir)) {
+        Some(workdir) => {
+            let relative = relative_path(path.strip_prefix(&workdir).ok()?);
+            (workdir, relative)
+        }
+        None if is_dir && path.join(".git").exists() => (path, String::new()),
+        None => return None,
+    };
+    let repository = cache
+        .repositories
+        .entry(workdir)
+        .or_insert_with_key(|workdir| Repository::open(workdir))
+        .as_ref()?;
+    Some(repository.status(&relative, is_dir))
+}
+
+#[derive(Debug, Default)]
+struct Cache {
+    // directory -> work tree it lies in
+    workdirs: HashMap<PathBuf, Option<PathBuf>>,
+    // work tree -> its statuses
+    repositories: HashMap<PathBuf, Option<Repository>>,
+}
+
+impl Cache {
+    fn workdir(&mut self, dir: &Path) -> Option<PathBuf> {
+        self.workdirs
+            .entry(dir.to_path_buf())
+            .or_insert_with(|| {
+                dir.ancestors()
+                    .find(|dir| dir.join(".git").exists())
+                    .map(Path::to_path_buf)
+            })
+            .clone()
+    }
+}
+
+// The path with its parent directory canonicalized but a final symlink kept.
+fn absolute(path: &Path) -> Option<PathBuf> {
+    match path.file_name() {
+        Some(name) => {
+            let parent = match path.parent() {
+                Some(parent) if !parent.as_os_str().is_empty() => parent,
+                _ => Path::new("."),
+            };
+            Some(parent.canonicalize().ok()?.join(name))
+        }
+        // `.`, `..` or `/`
+        None => path.canonicalize().ok(),
+    }
+}
+
+// `/` separated, like the paths in the index.
+fn relative_path(path: &Path) -> String {
+    path.iter()
+        .map(|c| c.to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+// Parent directories of a `/` separated path, deepest first, ending with the
+// work tree itself as `""`.
+fn parents(path: &str) -> impl Iterator<Item = &str> {
+    path.rmatch_indices('/')
+        .map(|(i, _)| &path[..i])
+        .chain((!path.is_empty()).then_some(""))
+}
+
+/// Statuses of a work tree: the index compared with the `HEAD` commit and
+/// with the files on disk, and the untracked files outside of ignore rules.
+#[derive(Debug, Default)]
+struct Repository {
+    // tracked and new files
+    files: HashMap<String, GitStatus>,
+    // rolled up from `files`, `""` is the work tree
+    dirs: HashMap<String, GitStatus>,
+    // untracked paths matched by an ignore rule; nothing below them is read
+    ignored: HashSet<String>,
+}
+
+impl Repository {
+    fn open(workdir: &Path) -> Option<Self> {
+        let git_dir = git_dir(workdir)?;
+        let common_dir = match fs::read_to_string(git_dir.join("commondir")) {
+            Ok(common) => git_dir.join(common.trim()),
+            Err(_) => git_dir.clone(),
+        };
+        if !uses_sha1(&common_dir) {
+            return None;
+        }
+        let file_mode = uses_file_mode(&common_dir);
+        let index_path = git_dir.join("index");
+        let index = match fs::read(&index_path) {
+            Ok(data) => parse_index(&data)?,
+            // nothing added yet
+            Err(_) => Vec::new(),
+        };
+        // entries changed in the same second as the index may look clean
+        let index_mtime = fs::metadata(&index_path)
+            .and_then(|md| md.modified())
+            .ok()
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .map_or(0, |d| d.as_secs());
+
+        let objects = Objects::new(&common_dir);
+        let mut head = HashMap::new();
+        if let Some(commit) = resolve_head(&git_dir, &common_dir) {
+            let tree = objects.commit_tree(&commit)?;
+            objects.read_tree(&tree, "", &mut head)?;
+        }
+
+        let mut repository = Self::default();
+        let mut conflicted = HashSet::new();
+        for entry in &index {
+            if entry.stage != 0 {
+                conflicted.insert(entry.path.as_str());
+                repository.set(&entry.path, GitStatus::Conflicted);
+                continue;
+            }
+            let mut status = match head.remove(&entry.path) {
+                _ if entry.intent_to_add => GitStatus::New,
+                Some((mode, oid)) if mode == entry.mode && oid == entry.oid => {
+                    GitStatus::Unmodified
+                }
+                _ => GitStatus::Staged,
+            };
+            if entry.mode != GITLINK_MODE
+                && !entry.skip_worktree
+                && !entry.assume_valid
+                && entry.changed_on_disk(&workdir.join(&entry.path), index_mtime, file_mode)
+            {
+                status = GitStatus::Modified;
+            }
+            repository.set(&entry.path, status);
+        }
+        // deleted in the index but still in `HEAD`
+        for path in head.keys() {
+            if !conflicted.contains(path.as_str()) {
+                repository.roll_up(path, GitStatus::Staged);
+            }
+        }
+
+        let tracked_dirs: HashSet<String> = index
+            .iter()
+            .flat_map(|entry| parents(&entry.path))
+            .map(String::from)
+            .collect();
+        let ignore = IgnoreFiles::for_git_root(workdir).enter(workdir);
+        repository.find_untracked(workdir, "", &ignore, &tracked_dirs);
+        Some(repository)
+    }
+    fn set(&mut self, path: &str, status: GitStatus) {
+        let current = self.files.entry(path.to_string()).or_insert(status);
+        *current = (*current).max(status);
+        self.roll_up(path, status);
+    }
+    fn roll_up(&mut self, path: &str, status: GitStatus) {
+        for dir in parents(path) {
+            let current = self.dirs.entry(dir.to_string()).or_insert(status);
+            *current = (*current).max(status);
+        }
+    }
+    // Walks the work tree like `git status`: untracked files are new unless
+    // ignored, ignored directories are not entered. Returns whether anything
+    // ignored was found.
+    fn find_untracked(
+        &mut self,
+        dir: &Path,
+        relative: &str,
+        ignore: &IgnoreFiles,
+        tracked_dirs: &HashSet<String>,
+    ) -> bool {
+        let Ok(read_dir) = fs::read_dir(dir) else {
+            return false;
+        };
+        let mut found_ignored = false;
+        for entry in read_dir.flatten() {
+            let name = entry.file_name();
+            let child = if relative.is_empty() {
+                name.to_string_lossy().into_owned()
+            } else {
+                format!("{relative}/{}", name.to_string_lossy())
+            };
+            if self.files.contains_key(&child) {
+                continue;
+            }
+            let path = entry.path();
+            let is_dir = entry.file_type().is_ok_and(|t| t.is_dir());
+            let tracked = tracked_dirs.contains(&child);
+            // `.git` directories are ignored, `.git` files of linked work
+            // trees are not listed by git either
+            if name == ".git" || ignore.is_ignored(&path, is_dir) {
+                self.ignored.insert(child);
+                found_ignored = true;
+            } else if !is_dir {
+                self.set(&child, GitStatus::New);
+            } else if !tracked && path.join(".git").exists() {
+                // another repository, new as a whole
+                self.dirs.insert(child.clone(), GitStatus::New);
+                self.roll_up(&child, GitStatus::New);
+            } else if self.find_untracked(&path, &child, &ignore.enter(&path), tracked_dirs) {
+                found_ignored = true;
+                // like `git status --ignored`, an untracked directory with
+                // nothing but ignored files is ignored as a whole
+                if !tracked && !self.dirs.contains_key(&child) {
+                    self.ignored.insert(child);
+                }
+            }
+        }
+        found_ignored
+    }
+    fn status(&self, path: &str, is_dir: bool) -> GitStatus {
+        let known = if is_dir {
+            self.dirs.get(path).or_else(|| self.files.get(path))
+        } else {
+            self.files.get(path)
+        };
+        if let Some(status) = known {
+            return *status;
+        }
+        let mut ancestors = std::iter::once(path).chain(parents(path));
+        if ancestors.any(|p| self.ignored.contains(p)) {
+            GitStatus::Ignored
+        } else if is_dir {
+            // empty, or created after the repository was read
+            GitStatus::Unmodified
+        } else {
+            GitStatus::New
+        }
+    }
+}
+
+// The repository directory of a work tree: `.git`, or where a `.git` file of a
+// linked work tree or submodule points.
+fn git_dir(workdir: &Path) -> Option<PathBuf> {
+    let dot_git = workdir.join(".git");
+    if dot_git.is_dir() {
+        return Some(dot_git);
+    }
+    let content = fs::read_to_string(&dot_git).ok()?;
+    let dir = content.strip_prefix("gitdir:")?.trim();
+    Some(workdir.join(dir))
+}
+
+// Whether object names are SHA-1 hashes, the only format read here: true
+// unless `extensions.objectFormat` of the repository says otherwise.
+fn uses_sha1(common_dir: &Path) -> bool {
+    config_value(common_dir, "extensions", "objectformat")
+        .is_none_or(|value| value.eq_ignore_ascii_case("sha1"))
+}
+
+// Whether the executable bit of files counts as a change: true unless
+// `core.fileMode` of the repository is false.
+fn uses_file_mode(common_dir: &Path) -> bool {
+    config_value(common_dir, "core", "filemode").is_none_or(|value| {
+        !["false", "no", "off", "0", ""]
+            .iter()
+            .any(|no| value.eq_ignore_ascii_case(no))
+    })
+}
+
+// The last value of `key` in `section` of the repository config, `true` for
+// a key without a value. Subsections, includes and the user's config are not
+// read.
+fn config_value(common_dir: &Path, section: &str, key: &str) -> Option<String> {
+    let config = fs::read_to_string(common_dir.join("config")).ok()?;
+    let mut in_section = false;
+    let mut value = None;
+    for line in config.lines().map(str::trim) {
+        if let Some(name) = line.strip_prefix('[') {
+            in_section = name
+                .trim_end_matches(']')
+                .trim()
+                .eq_ignore_ascii_case(section);
+        } else if in_section {
+            let (name, found) = line.split_once('=').unwrap_or((line, "true"));
+            if name.trim().eq_ignore_ascii_case(key) {
+                value = Some(found.trim().to_string());
+            }
+        }
+    }
+    value
+}
+
+// The commit `HEAD` points to, `None` on a branch without commits.
+fn resolve_head(git_dir: &Path, common_dir: &Path) -> Option<ObjectId> {
+    let mut reference = "HEAD".to_string();
+    // symbolic refs may point to symbolic refs
+    for _ in 0..5 {
+        let content = fs::read_to_string(git_dir.join(&reference))
+            .or_else(|_| fs::read_to_string(common_dir.join(&reference)))
+            .ok()
+            .or_else(|| packed_ref(common_dir, &reference))?;
+        let content = content.trim();
+        match content.strip_prefix("ref:") {
+            Some(target) => reference = target.trim().to_string(),
+            None => return parse_hex(content),
+        }
+    }
+    None
+}
+
+fn packed_ref(common_dir: &Path, reference: &str) -> Option<String> {
+    let packed = fs::read_to_string(common_dir.join("packed-refs")).ok()?;
+    packed
+        .lines()
+        .filter(|line| !line.starts_with('#') && !line.starts_with('^'))
+        .find_map(|line| {
+            let (oid, name) = line.split_once(' ')?;
+            (name == reference).then(|| oid.to_string())
+        })
+}
+
+fn parse_hex(hex: &str) -> Option<ObjectId> {
+    if hex.len() != HASH_LEN * 2 {
+        return None;
+    }
+    let mut oid = [0; HASH_LEN];
+    for (i, byte) in oid.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(hex.get(i * 2..i * 2 + 2)?, 16).ok()?;
+    }
+    Some(oid)
+}
+
+fn to_hex(oid: &ObjectId) -> String {
+    oid.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// One entry of the index.
+#[derive(Debug)]
+struct IndexEntry {
+    path: String,
+    oid: ObjectId,
+    mode: u32,
+    // non-zero for the sides of a merge conflict
+    stage: u16,
+    size: u32,
+    mtime: (u32, u32),
+    ctime: (u32, u32),
+    assume_valid: bool,
+    skip_worktree: bool,
+    // `git add -N`
+    intent_to_add: bool,
+}
+
+impl IndexEntry {
+    // Whether the file differs from the index. The stat data recorded in the
+    // index decides where it can, the content hash otherwise. Without
+    // `file_mode` a regular file keeps the executable bit of the index.
+    fn changed_on_disk(&self, path: &Path, index_mtime: u64, file_mode: bool) -> bool {
+        let Ok(metadata) = fs::symlink_metadata(path) else {
+            return true;
+        };
+        let mode = if metadata.file_type().is_symlink() {
+            SYMLINK_MODE
+        } else if metadata.is_file() {
+            if !file_mode && matches!(self.mode, 0o100644 | 0o100755) {
+                self.mode
+            } else if is_executable(&metadata) {
+                0o100755
+            } else {
+                0o100644
+            }
+        } else {
+            // replaced by a directory
+            return true;
+        };
+        if mode != self.mode || metadata.len() as u32 != self.size {
+            return true;
+        }
+        let (mtime, ctime) = stat_times(&metadata);
+        if mtime == self.mtime
+            && ctime.is_none_or(|ctime| ctime == self.ctime)
+            && u64::from(self.mtime.0) < index_mtime
+        {
+            return false;
+        }
+        let content = if mode == SYMLINK_MODE {
+            fs::read_link(path).map(|target| path_bytes(&target))
+        } else {
+            fs::read(path)
+        };
+        !content.is_ok_and(|content| hash_object("blob", &content) == self.oid)
+    }
+}
+
+#[cfg(unix)]
+fn is_executable(metadata: &fs::Metadata) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    // the owner's bit, as git checks it
+    metadata.permissions().mode() & 0o100 != 0
+}
+#[cfg(not(unix))]
+fn is_executable(_metadata: &fs::Metadata) -> bool {
+    false
+}
+
+// Modification and change time as seconds and nanoseconds; there is no change
+// time outside of unix.
+#[cfg(unix)]
+fn stat_times(metadata: &fs::Metadata) -> ((u32, u32), Option<(u32, u32)>) {
+    use std::os::unix::fs::MetadataExt;
+    (
+        (metadata.mtime() as u32, metadata.mtime_nsec() as u32),
+        Some((metadata.ctime() as u32, metadata.ctime_nsec() as u32)),
+    )
+}
+#[cfg(not(unix))]
+fn stat_times(metadata: &fs::Metadata) -> ((u32, u32), Option<(u32, u32)>) {
+    let mtime = metadata
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map_or((0, 0), |d| (d.as_secs() as u32, d.subsec_nanos()));
+    (mtime, None)
+}
+
+#[cfg(unix)]
+fn path_bytes(path: &Path) -> Vec<u8> {
+    use std::os::unix::ffi::OsStrExt;
+    path.as_os_str().as_bytes().to_vec()
+}
+#[cfg(not(unix))]
+fn path_bytes(path: &Path) -> Vec<u8> {
+    relative_path(path).into_bytes()
+}
+
+fn hash_object(kind: &str, content: &[u8]) -> ObjectId {
+    let mut sha = sha1_smol::Sha1::new();
+    sha.update(format!("{kind} {}\0", content.len()).as_bytes());
+    sha.update(content);
+    sha.digest().bytes()
+}
+
+/// Reads big-endian numbers off the front of a byte slice.
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        if self.data.len() < len {
+            return None;
+        }
+        let (head, rest) = self.data.split_at(len);
+        self.data = rest;
+        Some(head)
+    }
+    fn u16(&mut self) -> Option<u16> {
+        self.take(2).map(|b| u16::from_be_bytes([b[0], b[1]]))
+    }
+    fn u32(&mut self) -> Option<u32> {
+        self.take(4)
+            .map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+    }
+    fn oid(&mut self) -> Option<ObjectId> {
+        self.take(HASH_LEN)?.try_into().ok()
+    }
+    // `0`-terminated, the terminator is consumed
+    fn until_nul(&mut self) -> Option<&'a [u8]> {
+        let end = self.data.iter().position(|&b| b == 0)?;
+        let text = self.take(end)?;
+        self.take(1)?;
+        Some(text)
+    }
+    // the variable length offsets of index v4 names and `OFS_DELTA` objects
+    fn offset(&mut self) -> Option<usize> {
+        let mut byte = self.take(1)?[0];
+        let mut value = usize::from(byte & 0x7f);
+        while byte & 0x80 != 0 {
+            byte = self.take(1)?[0];
+            value = ((value + 1) << 7) | usize::from(byte & 0x7f);
+        }
+        Some(value)
+    }
+}
+
+// The entries of an index file in version 2, 3 or 4; extensions are skipped.
+fn parse_index(data: &[u8]) -> Option<Vec<IndexEntry>> {
+    let mut reader = Reader { data };
+    if reader.take(4)? != b"DIRC" {
+        return None;
+    }
+    let version = reader.u32()?;
+    if !(2..=4).contains(&version) {
+        return None;
+    }
+    let count = reader.u32()?;
+    // the count is not trusted: no more entries than the data can hold, each
+    // at least 62 bytes long
+    let mut entries = Vec::with_capacity((count as usize).min(data.len() / 62));
+    let mut previous: Vec<u8> = Vec::new();
+    for _ in 0..count {
+        let start = reader.data.len();
+        let ctime = (reader.u32()?, reader.u32()?);
+        let mtime = (reader.u32()?, reader.u32()?);
+        // dev, ino
+        reader.take(8)?;
+        let mode = reader.u32()?;
+        // uid, gid
+        reader.take(8)?;
+        let size = reader.u32()?;
+        let oid = reader.oid()?;
+        let flags = reader.u16()?;
+        let extended = if version >= 3 && flags & 0x4000 != 0 {
+            reader.u16()?
+        } else {
+            0
+        };
+        let name = if version == 4 {
+            // the previous name with `strip` bytes cut off, then the new suffix
+            let strip = reader.offset()?;
+            let mut name = previous[..previous.len().checked_sub(strip)?].to_vec();
+            name.extend_from_slice(reader.until_nul()?);
+            name
+        } else {
+            let name = reader.until_nul()?.to_vec();
+            // entries are padded with NULs to a multiple of eight bytes
+            let read = start - reader.data.len();
+            reader.take((8 - read % 8) % 8)?;
+            name
+        };
+        entries.push(IndexEntry {
+            path: String::from_utf8_lossy(&name).into_owned(),
+            oid,
+            mode,
+            stage: (flags >> 12) & 0x3,
+            size,
+            mtime,
+            ctime,
+            assume_valid: flags & 0x8000 != 0,
+            skip_worktree: extended & 0x4000 != 0,
+            intent_to_add: extended & 0x2000 != 0,
+        });
+        previous = name;
+    }
+    Some(entries)
+}
+
+/// The object database: loose objects and pack files.
+struct Objects {
+    dir: PathBuf,
+    // `.idx` files with their fan-out tables read
+    packs: Vec<PackIndex>,
+}
+
+impl Objects {
+    fn new(common_dir: &Path) -> Self {
+        let dir = common_dir.join("objects");
+        let packs = fs::read_dir(dir.join("pack"))
+            .into_iter()
+            .flatten()
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "idx"))
+            .filter_map(|path| PackIndex::read(&path))
+            .collect();
+        Self { dir, packs }
+    }
+    // Type and content of an object.
+    fn read(&self, oid: &ObjectId) -> Option<(ObjectKind, Vec<u8>)> {
+        let hex = to_hex(oid);
+        if let Ok(data) = fs::read(self.dir.join(&hex[..2]).join(&hex[2..])) {
+            let data = inflate(&data, None)?;
+            let header_end = data.iter().position(|&b| b == 0)?;
+            let kind = match data[..header_end].split(|&b| b == b' ').next()? {
+                b"commit" => ObjectKind::Commit,
+                b"tree" => ObjectKind::Tree,
+                b"blob" => ObjectKind::Blob,
+                b"tag" => ObjectKind::Tag,
+                _ => return None,
+            };
+            return Some((kind, data[header_end + 1..].to_vec()));
+        }
+        self.packs.iter().find_map(|pack| {
+            let offset = pack.find(oid)?;
+            self.read_packed(pack, offset)
+        })
+    }
+    // Reads the object at `offset` in `pack`, following its chain of deltas
+    // down to a base object and applying them back up. Chains longer than
+    // `MAX_DELTA_DEPTH` are taken for the cycles of a corrupt pack.
+    fn read_packed(&self, pack: &PackIndex, offset: u64) -> Option<(ObjectKind, Vec<u8>)> {
+        let mut deltas = Vec::new();
+        let mut next = pack.read_at(offset)?;
+        let (kind, mut data) = loop {
+            if deltas.len() > MAX_DELTA_DEPTH {
+                return None;
+            }
+            let (entry, data) = next;
+            next = match entry {
+                PackEntry::Base(kind) => break (kind, data),
+                PackEntry::OfsDelta(pack, base) => {
+                    deltas.push(data);
+                    pack.read_at(base)?
+                }
+                PackEntry::RefDelta(base) => {
+                    deltas.push(data);
+                    match self
+                        .packs
+                        .iter()
+                        .find_map(|pack| Some((pack, pack.find(&base)?)))
+                    {
+                        Some((pack, offset)) => pack.read_at(offset)?,
+                        // a loose base is never a delta itself
+                        None => break self.read(&base)?,
+                    }
+                }
+            };
+        };
+        for delta in deltas.iter().rev() {
+            data = apply_delta(&data, delta)?;
+        }
+        Some((kind, data))
+    }
+    fn commit_tree(&self, commit: &ObjectId) -> Option<ObjectId> {
+        let (kind, data) = self.read(commit)?;
+        if kind != ObjectKind::Commit {
+            return None;
+        }
+        let hex = data.strip_prefix(b"tree ")?.get(..HASH_LEN * 2)?;
+        parse_hex(std::str::from_utf8(hex).ok()?)
+    }
+    // Adds every blob, symlink and submodule below `tree` to `files`.
+    fn read_tree(
+        &self,
+        tree: &ObjectId,
+        prefix: &str,
+        files: &mut HashMap<String, (u32, ObjectId)>,
+    ) -> Option<()> {
+        let (kind, data) = self.read(tree)?;
+        if kind != ObjectKind::Tree {
+            return None;
+        }
+        let mut reader = Reader { data: &data };
+        while !reader.data.is_empty() {
+            let entry = reader.until_nul()?;
+            let space = entry.iter().position(|&b| b == b' ')?;
+            let mode = u32::from_str_radix(std::str::from_utf8(&entry[..space]).ok()?, 8).ok()?;
+            let name = String::from_utf8_lossy(&entry[space + 1..]);
+            let path = if prefix.is_empty() {
+                name.into_owned()
+            } else {
+                format!("{prefix}/{name}")
+            };
+            let oid = reader.oid()?;
+            if mode == 0o40000 {
+                self.read_tree(&oid, &path, files)?;
+            } else {
+                files.insert(path, (mode, oid));
+            }
+        }
+        Some(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ObjectKind {
+    Commit,
+    Tree,
+    Blob,
+    Tag,
+}
+
+impl ObjectKind {
+    fn from_pack_type(kind: u8) -> Option<Self> {
+        match kind {
+            1 => Some(ObjectKind::Commit),
+            2 => Some(ObjectKind::Tree),
+            3 => Some(ObjectKind::Blob),
+            4 => Some(ObjectKind::Tag),
+            _ => None,
+        }
+    }
+}
+
+// Decompresses a zlib stream; trailing data after its end is ignored.
+fn inflate(data: &[u8], size: Option<usize>) -> Option<Vec<u8>> {
+    match size {
+        Some(size) => miniz_oxide::inflate::decompress_to_vec_zlib_with_limit(data, size).ok(),
+        None => miniz_oxide::inflate::decompress_to_vec_zlib(data).ok(),
+    }
+}
+
+/// A version 2 `.idx` file and the pack next to it.
+struct PackIndex {
+    pack: PathBuf,
+    data: Vec<u8>,
+    count: usize,
+}
+
+impl PackIndex {
+    const HEADER: usize = 8;
+    const FANOUT: usize = 256 * 4;
+
+    fn read(path: &Path) -> Option<Self> {
+        let data = fs::read(path).ok()?;
+        if data.get(..8)? != b"\xfftOc\0\0\0\x02" {
+            return None;
+        }
+        let mut fanout = Reader {
+            data: data.get(Self::HEADER + 255 * 4..Self::HEADER + Self::FANOUT)?,
+        };
+        let count = fanout.u32()? as usize;
+        Some(Self {
+            pack: path.with_extension("pack"),
+            data,
+            count,
+        })
+    }
+    fn u32_at(&self, offset: usize) -> Option<u32> {
+        Reader {
+            data: self.data.get(offset..)?,
+        }
+        .u32()
+    }
+    // Offset of the object in the pack file.
+    fn find(&self, oid: &ObjectId) -> Option<u64> {
+        let first = usize::from(oid[0]);
+        let start = match first {
+            0 => 0,
+            _ => self.u32_at(Self::HEADER + (first - 1) * 4)? as usize,
+        };
+        let end = self.u32_at(Self::HEADER + first * 4)? as usize;
+        let ids = Self::HEADER + Self::FANOUT;
+        let id_at = |i: usize| self.data.get(ids + i * HASH_LEN..ids + (i + 1) * HASH_LEN);
+
+        let (mut low, mut high) = (start, end);
+        let position = loop {
+            if low >= high {
+                return None;
+            }
+            let middle = (low + high) / 2;
+            match id_at(middle)?.cmp(oid.as_slice()) {
+                std::cmp::Ordering::Equal => break middle,
+                std::cmp::Ordering::Less => low = middle + 1,
+                std::cmp::Ordering::Greater => high = middle,
+            }
+        };
+        // after the ids and their CRCs
+        let offsets = ids + self.count * (HASH_LEN + 4);
+        let offset = self.u32_at(offsets + position * 4)?;
+        if offset & 0x8000_0000 == 0 {
+            return Some(u64::from(offset));
+        }
+        let large = offsets + self.count * 4 + (offset & 0x7fff_ffff) as usize * 8;
+        let high = self.u32_at(large)?;
+        let low = self.u32_at(large + 4)?;
+        Some((u64::from(high) << 32) | u64::from(low))
+    }
+    // Reads the object at `offset`, leaving deltas for the caller to resolve.
+    fn read_at(&self, offset: u64) -> Option<(PackEntry<'_>, Vec<u8>)> {
+        let mut file = File::open(&self.pack).ok()?;
+        // large enough for the header and a base reference
+        let header = read_window(&mut file, offset, 32)?;
+        let mut reader = Reader { data: &header };
+        let mut byte = reader.take(1)?[0];
+        let kind = (byte >> 4) & 0x7;
+        let mut size = usize::from(byte & 0x0f);
+        let mut shift = 4;
+        while byte & 0x80 != 0 {
+            byte = reader.take(1)?[0];
+            size |= usize::from(byte & 0x7f) << shift;
+            shift += 7;
+        }
+        let entry = match kind {
+            // OFS_DELTA: the base lies before this object
+            6 => match reader.offset()? as u64 {
+                // a delta against itself
+                0 => return None,
+                distance => PackEntry::OfsDelta(self, offset.checked_sub(distance)?),
+            },
+            // REF_DELTA: the base is named by its id
+            7 => PackEntry::RefDelta(reader.oid()?),
+            _ => PackEntry::Base(ObjectKind::from_pack_type(kind)?),
+        };
+        let data_start = offset + (header.len() - reader.data.len()) as u64;
+        Some((entry, inflate_at(&mut file, data_start, size)?))
+    }
+}
+
+/// How an object is stored in a pack: whole, or as a delta against a base.
+enum PackEntry<'a> {
+    Base(ObjectKind),
+    // the base at an offset in the same pack
+    OfsDelta(&'a PackIndex, u64),
+    RefDelta(ObjectId),
+}
+
+fn read_window(file: &mut File, offset: u64, len: usize) -> Option<Vec<u8>> {
+    file.seek(SeekFrom::Start(offset)).ok()?;
+    let mut buf = Vec::with_capacity(len);
+    file.take(len as u64).read_to_end(&mut buf).ok()?;
+    Some(buf)
+}
+
+// Inflates an object of `size` bytes without knowing its compressed length:
+// the window read is grown until the stream fits or the file ends.
+fn inflate_at(file: &mut File, offset: u64, size: usize) -> Option<Vec<u8>> {
+    let mut len = size + 64;
+    loop {
+        let window = read_window(file, offset, len)?;
+        if let Some(data) = inflate(&window, Some(size)) {
+            return (data.len() == size).then_some(data);
+        }
+        if window.len() < len {
+            return None;
+        }
+        len *= 2;
+    }
+}
+
+// Applies a git delta: the sizes of base and result, then instructions to copy
+// ranges of the base or insert literal bytes.
+fn apply_delta(base: &[u8], delta: &[u8]) -> Option<Vec<u8>> {
+    fn size(reader: &mut Reader) -> Option<usize> {
+        let mut size = 0;
+        let mut shift = 0;
+        loop {
+            let byte = reader.take(1)?[0];
+            size |= usize::from(byte & 0x7f) << shift;
+            shift += 7;
+            if byte & 0x80 == 0 {
+                return Some(size);
+            }
+        }
+    }
+
+    let mut reader = Reader { data: delta };
+    if size(&mut reader)? != base.len() {
+        return None;
+    }
+    let result_size = size(&mut reader)?;
+    let mut result = Vec::with_capacity(result_size);
+    while let Some(&[op]) = reader.take(1) {
+        if op & 0x80 != 0 {
+            // bits 0-3 select the offset bytes, 4-6 the size bytes
+            let mut offset = 0usize;
+            let mut len = 0usize;
+            for i in 0..4 {
+                if op & (1 << i) != 0 {
+                    offset |= usize::from(reader.take(1)?[0]) << (i * 8);
+                }
+            }
+            for i in 0..3 {
+                if op & (1 << (4 + i)) != 0 {
+                    len |= usize::from(reader.take(1)?[0]) << (i * 8);
+                }
+            }
+            if len == 0 {
+                len = 0x10000;
+            }
+            result.extend_from_slice(base.get(offset..offset.checked_add(len)?)?);
+        } else if op != 0 {
+            result.extend_from_slice(reader.take(usize::from(op))?);
+        } else {
+            return None;
+        }
+    }
+    (result.len() == result_size).then_some(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture(name: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests")
+            .join("fixtures")
+            .join("git")
+            .join(name)
+    }
+
+    fn oid(hex: &str) -> ObjectId {
+        parse_hex(hex).unwrap()
+    }
+
+    // `path -> blob` of the fixtures, written by `git add` in this order
+    const INDEX: [(&str, &str); 4] = [
+        ("README", "8178c76d627cade75005b40711b92f4177bc6cfc"),
+        ("src/a.rs", "82a3da18326fabb51616b411a6b943ffef90da1a"),
+        (
+            "src/bin/main.rs",
+            "f328e4d9d04c31d0d70d16d21a07d1613be9d577",
+        ),
+        ("src/lib.rs", "0168964c15d7c85f7abd4d08ed54cde8e9751624"),
+    ];
+
+    fn assert_entries(entries: &[IndexEntry], expected: &[(&str, &str)]) {
+        let found: Vec<_> = entries.iter().map(|e| (e.path.as_str(), e.oid)).collect();
+        let expected: Vec<_> = expected
+            .iter()
+            .map(|&(path, hex)| (path, oid(hex)))
+            .collect();
+        assert_eq!(found, expected);
+    }
+
+    #[test]
+    fn parse_index_v2_test() {
+        let entries = parse_index(&fs::read(fixture("index-v2")).unwrap()).unwrap();
+        assert_entries(&entries, &INDEX);
+        assert!(entries.iter().all(|e| e.mode == 0o100644 && e.stage == 0));
+        assert_eq!(entries[0].size, 7);
+    }
+
+    #[test]
+    fn parse_index_v3_test() {
+        // extended flags: `README` skips the work tree, `new.txt` was added with `-N`
+        let entries = parse_index(&fs::read(fixture("index-v3")).unwrap()).unwrap();
+        let mut expected = INDEX.to_vec();
+        expected.insert(1, ("new.txt", "e69de29bb2d1d6434b8b29ae775ad8c2e48c5391"));
+        assert_entries(&entries, &expected);
+        assert!(entries[0].skip_worktree && !entries[0].intent_to_add);
+        assert!(entries[1].intent_to_add && !entries[1].skip_worktree);
+        assert!(
+            entries[2..]
+                .iter()
+                .all(|e| !e.skip_worktree && !e.intent_to_add)
+        );
+    }
+
+    #[test]
+    fn parse_index_v4_test() {
+        // names are stored as the part that differs from the previous one
+        let entries = parse_index(&fs::read(fixture("index-v4")).unwrap()).unwrap();
+        assert_entries(&entries, &INDEX);
+    }
+
+    #[test]
+    fn parse_index_invalid_test() {
+        let data = fs::read(fixture("index-v2")).unwrap();
+        assert!(parse_index(&data[..data.len() / 2]).is_none());
+        assert!(parse_index(b"DIRC\0\0\0\x05\0\0\0\0").is_none());
+        assert!(parse_index(b"CRID\0\0\0\x02\0\0\0\0").is_none());
+        // a truncated header claiming far more entries than there is memory for
+        assert!(parse_index(b"DIRC\0\0\0\x02\xff\xff\xff\xff").is_none());
+        let mut huge = data[..12].to_vec();
+        huge[8..12].copy_from_slice(&u32::MAX.to_be_bytes());
+        huge.extend_from_slice(&data[12..]);
+        assert!(parse_index(&huge).is_none());
+    }
+
+    #[test]
+    fn apply_delta_test() {
+        let base = b"0123456789";
+        // sizes 10 and 9, copy 4 bytes at 2, insert "ab", copy 3 bytes at 7
+        let delta = b"\x0a\x09\x91\x02\x04\x02ab\x91\x07\x03";
+        assert_eq!(apply_delta(base, delta).unwrap(), b"2345ab789");
+
+        // wrong base size, copy out of the base, reserved instruction
+        assert!(apply_delta(base, b"\x0b\x02\x02ab").is_none());
+        assert!(apply_delta(base, b"\x0a\x04\x91\x08\x04").is_none());
+        assert!(apply_delta(base, b"\x0a\x02\x00").is_none());
+        // fewer bytes than the result size
+        assert!(apply_delta(base, b"\x0a\x03\x02ab").is_none());
+    }
+
+    #[test]
+    fn pack_index_find_test() {
+        let pack = PackIndex::read(&fixture("pack.idx")).unwrap();
+        assert_eq!(pack.count, 9);
+        let objects = Objects {
+            dir: PathBuf::new(),
+            packs: Vec::new(),
+        };
+
+        // three versions of a file, the older ones stored as a chain of deltas
+        for (hex, offset) in [
+            ("e162fb0fa6d1ed4691a416e7c48d9cb19eb27c07", 361),
+            ("56361596f1b65a93f739052bec31dfaa09809989", 817),
+            ("aa5e3f802c6a6d3eb7eac845d2293dec38ccfff1", 895),
+        ] {
+            let oid = oid(hex);
+            assert_eq!(pack.find(&oid), Some(offset));
+            let (kind, data) = objects.read_packed(&pack, offset).unwrap();
+            assert_eq!(kind, ObjectKind::Blob);
+            assert_eq!(hash_object("blob", &data), oid);
+        }
+        assert_eq!(
+            pack.find(&oid("bf8b07cbe344d45399a46f1ee234c3460f66876d")),
+            Some(12)
+        );
+        assert_eq!(
+            pack.find(&oid("0000000000000000000000000000000000000000")),
+            None
+        );
+        assert_eq!(
+            pack.find(&oid("ffffffffffffffffffffffffffffffffffffffff")),
+            None
+        );
+        assert_eq!(
+            pack.find(&oid("e162fb0fa6d1ed4691a416e7c48d9cb19eb27c08")),
+            None
+        );
+    }
+
+    // A pack holding `object` at offset 12 and its `.idx` naming it `oid`.
+    fn write_pack(dir: &Path, oid: &ObjectId, object: &[u8]) -> PathBuf {
+        let mut pack = b"PACK\0\0\0\x02\0\0\0\x01".to_vec();
+        pack.extend_from_slice(object);
+        fs::write(dir.join("corrupt.pack"), pack).unwrap();
+
+        let mut idx = b"\xfftOc\0\0\0\x02".to_vec();
+        for byte in 0..=255u8 {
+            let count: u32 = (byte >= oid[0]).into();
+            idx.extend_from_slice(&count.to_be_bytes());
+        }
+        idx.extend_from_slice(oid);
+        // CRC, then the offset
+        idx.extend_from_slice(&[0; 4]);
+        idx.extend_from_slice(&12u32.to_be_bytes());
+        let path = dir.join("corrupt.idx");
+        fs::write(&path, idx).unwrap();
+        path
+    }
+
+    #[test]
+    fn pack_delta_cycle_test() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let oid = oid("56361596f1b65a93f739052bec31dfaa09809989");
+        let objects = |idx: PathBuf| Objects {
+            dir: dir.path().to_path_buf(),
+            packs: vec![PackIndex::read(&idx).unwrap()],
+        };
+
+        // an OFS_DELTA whose base is itself: distance 0
+        let idx = write_pack(dir.path(), &oid, &[0x63, 0x00]);
+        assert!(objects(idx).read(&oid).is_none());
+
+        // a REF_DELTA whose base is itself
+        let mut object = vec![0x73];
+        object.extend_from_slice(&oid);
+        let idx = write_pack(dir.path(), &oid, &object);
+        assert!(objects(idx).read(&oid).is_none());
+    }
+}
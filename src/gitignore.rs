@@ -0,0 +1,229 @@
+use std::{
+    env,
+    ffi::OsStr,
+    fs,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+use crate::pattern::Glob;
+
+// ignore files read in every directory, later ones take precedence
+const IGNORE_FILES: [&str; 3] = [".gitignore", ".ignore", ".fdignore"];
+// the only ones git itself reads
+const GIT_IGNORE_FILES: [&str; 1] = [".gitignore"];
+
+/// One line of an ignore file.
+#[derive(Debug)]
+struct Rule {
+    glob: Glob,
+    // `!pattern`: re-includes what an earlier rule ignored
+    negated: bool,
+    // `pattern/`: matches directories only
+    dir_only: bool,
+    // a `/` before the end: matched against the path from the ignore file's
+    // directory, otherwise against the name at any depth
+    anchored: bool,
+}
+
+impl Rule {
+    fn parse(line: &str) -> Option<Self> {
+        let line = trim_trailing_spaces(line.trim_end_matches('\r'));
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+        let (negated, line) = match line.strip_prefix('!') {
+            Some(rest) => (true, rest),
+            None => (false, line),
+        };
+        let (dir_only, line) = match line.strip_suffix('/') {
+            Some(rest) => (true, rest),
+            None => (false, line),
+        };
+        let anchored = line.contains('/');
+        let line = line.strip_prefix('/').unwrap_or(line);
+        if line.is_empty() {
+            return None;
+        }
+        Some(Self {
+            // `\#` and `\!` are escapes the glob understands
+            glob: Glob::new(line),
+            negated,
+            dir_only,
+            anchored,
+        })
+    }
+    fn matches(&self, relative: &str, name: &str, is_dir: bool) -> bool {
+        if self.dir_only && !is_dir {
+            return false;
+        }
+        if self.anchored {
+            self.glob.matches_str(relative)
+        } else {
+            self.glob.matches_str(name)
+        }
+    }
+}
+
+// Trailing spaces are dropped unless escaped with a backslash.
+fn trim_trailing_spaces(line: &str) -> &str {
+    let mut end = line.len();
+    while line[..end].ends_with(' ') && !line[..end - 1].ends_with('\\') {
+        end -= 1;
+    }
+    &line[..end]
+}
+
+/// The rules of one ignore file.
+#[derive(Debug)]
+struct RuleSet {
+    // walked path the rules apply below
+    base: PathBuf,
+    // path of `base` from the directory of the ignore file, for files above
+    // the start of the walk
+    prefix: PathBuf,
+    rules: Vec<Rule>,
+}
+
+impl RuleSet {
+    fn read(file: &Path, base: &Path, prefix: &Path) -> Option<Self> {
+        let text = fs::read_to_string(file).ok()?;
+        let rules: Vec<_> = text.lines().filter_map(Rule::parse).collect();
+        (!rules.is_empty()).then(|| Self {
+            base: base.to_path_buf(),
+            prefix: prefix.to_path_buf(),
+            rules,
+        })
+    }
+    // `Some(true)` if the last matching rule ignores `path`, `Some(false)` if
+    // it re-includes it, `None` if no rule matches.
+    fn decide(&self, path: &Path, is_dir: bool) -> Option<bool> {
+        let relative = self.prefix.join(path.strip_prefix(&self.base).ok()?);
+        let relative = relative.to_string_lossy().replace('\\', "/");
+        let name = path.file_name()?.to_string_lossy();
+        self.rules
+            .iter()
+            .rev()
+            .find(|rule| rule.matches(&relative, &name, is_dir))
+            .map(|rule| !rule.negated)
+    }
+}
+
+/// Ignore rules in effect in one directory of a walk: the global excludes
+/// file, `.git/info/exclude`, and the `.gitignore`, `.ignore` and `.fdignore`
+/// files of the directory and its parents within the repository. Like in git,
+/// deeper files override higher ones and the last matching line wins.
+#[derive(Debug, Clone, Default)]
+pub struct IgnoreFiles {
+    // lowest precedence first
+    sets: Vec<Rc<RuleSet>>,
+    // names of the ignore files read in each directory
+    files: &'static [&'static str],
+}
+
+impl IgnoreFiles {
+    /// Rules of the directories above `root`, the start of a walk. The files
+    /// of `root` itself are read by [`IgnoreFiles::enter`].
+    pub fn for_root(root: &Path) -> Self {
+        Self::with_files(root, &IGNORE_FILES)
+    }
+    /// Like [`IgnoreFiles::for_root`], but with the rules git applies only:
+    /// `.ignore` and `.fdignore` files are not read.
+    pub fn for_git_root(root: &Path) -> Self {
+        Self::with_files(root, &GIT_IGNORE_FILES)
+    }
+    fn with_files(root: &Path, files: &'static [&'static str]) -> Self {
+        let mut ignore = Self {
+            files,
+            ..Self::default()
+        };
+        let Ok(canonical) = root.canonicalize() else {
+            return ignore;
+        };
+        let Some(repository) = canonical.ancestors().find(|dir| dir.join(".git").exists()) else {
+            return ignore;
+        };
+        let from_repository = canonical.strip_prefix(repository).unwrap_or(Path::new(""));
+
+        if let Some(excludes) = global_excludes_file() {
+            ignore.push(RuleSet::read(&excludes, root, from_repository));
+        }
+        let exclude = repository.join(".git").join("info").join("exclude");
+        ignore.push(RuleSet::read(&exclude, root, from_repository));
+
+        // parents from the repository root down, `root` excluded
+        let mut parents: Vec<_> = canonical
+            .ancestors()
+            .skip(1)
+            .take_while(|dir| dir.starts_with(repository))
+            .collect();
+        parents.reverse();
+        for dir in parents {
+            let prefix = canonical.strip_prefix(dir).unwrap_or(Path::new(""));
+            for file in files {
+                ignore.push(RuleSet::read(&dir.join(file), root, prefix));
+            }
+        }
+        ignore
+    }
+    /// Rules for the contents of `dir`: these and the ignore files in `dir`.
+    pub fn enter(&self, dir: &Path) -> Self {
+        let mut ignore = self.clone();
+        for file in self.files {
+            ignore.push(RuleSet::read(&dir.join(file), dir, Path::new("")));
+        }
+        ignore
+    }
+    pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        // the repository itself is never listed
+        if is_dir && path.file_name() == Some(OsStr::new(".git")) {
+            return true;
+        }
+        self.sets
+            .iter()
+            .rev()
+            .find_map(|set| set.decide(path, is_dir))
+            .unwrap_or(false)
+    }
+    fn push(&mut self, set: Option<RuleSet>) {
+        self.sets.extend(set.map(Rc::new));
+    }
+}
+
+// `core.excludesFile` of the user's git configuration, or git's default of
+// `$XDG_CONFIG_HOME/git/ignore`.
+fn global_excludes_file() -> Option<PathBuf> {
+    let home = env::var_os("HOME").map(PathBuf::from);
+    if let Some(home) = &home
+        && let Ok(config) = fs::read_to_string(home.join(".gitconfig"))
+        && let Some(file) = core_excludes_file(&config)
+    {
+        return Some(match file.strip_prefix("~/") {
+            Some(rest) => home.join(rest),
+            None => PathBuf::from(file),
+        });
+    }
+    let config_home = env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| home.map(|home| home.join(".config")))?;
+    Some(config_home.join("git").join("ignore"))
+}
+
+fn core_excludes_file(config: &str) -> Option<String> {
+    let mut in_core = false;
+    for line in config.lines().map(str::trim) {
+        if let Some(section) = line.strip_prefix('[') {
+            in_core = section
+                .trim_end_matches(']')
+                .trim()
+                .eq_ignore_ascii_case("core");
+        } else if in_core
+            && let Some((key, value)) = line.split_once('=')
+            && key.trim().eq_ignore_ascii_case("excludesfile")
+        {
+            return Some(value.trim().trim_matches('"').to_string());
+        }
+    }
+    None
+}
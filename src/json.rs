@@ -1,135 +1,441 @@
-use crate::files::FileSystemEntry;
+use std::{
+    ffi::OsStr,
+    io::{self, Write},
+};
 
-pub trait Serializer {
-    fn short_json(&self) -> String;
-    fn long_json(&self) -> String;
+use crate::{
+    files::{FileSystemEntry, MetaData, SpecialKind},
+    time::Time,
+};
+
+/// Version of the JSON output shape, bumped on every breaking change.
+///
+/// It is written as `schema_version` into the top-level object of `-j`/`-J`
+/// output and into every JSON Lines record.
+pub const SCHEMA_VERSION: u64 = 8;
+
+/// JSON Schema document describing every JSON output of `fls`.
+pub const SCHEMA: &str = include_str!("schema.json");
+
+/// Streaming JSON encoder.
+///
+/// Values are written straight into the underlying `io::Write`; the writer only
+/// keeps track of the open containers so it knows where commas, newlines and
+/// indentation belong. Strings are escaped according to RFC 8259.
+pub struct JsonWriter<W: Write> {
+    out: W,
+    pretty: bool,
+    // one entry per open container: `true` once it holds at least one value
+    stack: Vec<bool>,
+    after_key: bool,
 }
 
-fn add_quotes(s: &str) -> String {
-    format!("\"{s}\"")
+impl<W: Write> JsonWriter<W> {
+    pub fn new(out: W, pretty: bool) -> Self {
+        Self {
+            out,
+            pretty,
+            stack: Vec::new(),
+            after_key: false,
+        }
+    }
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+    fn newline_indent(&mut self) -> io::Result<()> {
+        if self.pretty {
+            self.out.write_all(b"\n")?;
+            for _ in 0..self.stack.len() {
+                self.out.write_all(b"  ")?;
+            }
+        }
+        Ok(())
+    }
+    fn before_value(&mut self) -> io::Result<()> {
+        if self.after_key {
+            self.after_key = false;
+            return Ok(());
+        }
+        if let Some(has_items) = self.stack.last_mut() {
+            let had_items = *has_items;
+            *has_items = true;
+            if had_items {
+                self.out.write_all(b",")?;
+            }
+            self.newline_indent()?;
+        }
+        Ok(())
+    }
+    fn begin(&mut self, open: &[u8]) -> io::Result<()> {
+        self.before_value()?;
+        self.out.write_all(open)?;
+        self.stack.push(false);
+        Ok(())
+    }
+    fn end(&mut self, close: &[u8]) -> io::Result<()> {
+        if self.stack.pop().unwrap_or(false) {
+            self.newline_indent()?;
+        }
+        self.out.write_all(close)
+    }
+    pub fn begin_object(&mut self) -> io::Result<()> {
+        self.begin(b"{")
+    }
+    pub fn end_object(&mut self) -> io::Result<()> {
+        self.end(b"}")
+    }
+    pub fn begin_array(&mut self) -> io::Result<()> {
+        self.begin(b"[")
+    }
+    pub fn end_array(&mut self) -> io::Result<()> {
+        self.end(b"]")
+    }
+    pub fn key(&mut self, key: &str) -> io::Result<()> {
+        self.before_value()?;
+        write_escaped(&mut self.out, key)?;
+        self.out.write_all(if self.pretty { b": " } else { b":" })?;
+        self.after_key = true;
+        Ok(())
+    }
+    pub fn string(&mut self, value: &str) -> io::Result<()> {
+        self.before_value()?;
+        write_escaped(&mut self.out, value)
+    }
+    pub fn uint(&mut self, value: u64) -> io::Result<()> {
+        self.before_value()?;
+        write!(self.out, "{value}")
+    }
+    pub fn int(&mut self, value: i64) -> io::Result<()> {
+        self.before_value()?;
+        write!(self.out, "{value}")
+    }
+    pub fn bool(&mut self, value: bool) -> io::Result<()> {
+        self.before_value()?;
+        self.out.write_all(if value { b"true" } else { b"false" })
+    }
+    pub fn null(&mut self) -> io::Result<()> {
+        self.before_value()?;
+        self.out.write_all(b"null")
+    }
+    pub fn field_str(&mut self, key: &str, value: &str) -> io::Result<()> {
+        self.key(key)?;
+        self.string(value)
+    }
+    /// Writes a file name as is, not quoted like in text output. A name that is
+    /// not valid unicode is decoded lossily, and its bytes are written as
+    /// `<key>_bytes` too so it can be recovered.
+    pub fn field_name(&mut self, key: &str, name: &OsStr) -> io::Result<()> {
+        self.field_str(key, &name.to_string_lossy())?;
+        if name.to_str().is_none() {
+            self.key(&format!("{key}_bytes"))?;
+            self.begin_array()?;
+            for &byte in name.as_encoded_bytes() {
+                self.uint(u64::from(byte))?;
+            }
+            self.end_array()?;
+        }
+        Ok(())
+    }
+    pub fn field_opt_str(&mut self, key: &str, value: Option<&str>) -> io::Result<()> {
+        self.key(key)?;
+        match value {
+            Some(value) => self.string(value),
+            None => self.null(),
+        }
+    }
+    pub fn field_uint(&mut self, key: &str, value: u64) -> io::Result<()> {
+        self.key(key)?;
+        self.uint(value)
+    }
+    pub fn field_bool(&mut self, key: &str, value: bool) -> io::Result<()> {
+        self.key(key)?;
+        self.bool(value)
+    }
 }
 
-fn form_field(buf: &mut String, k: &str, v: String, is_last: bool) {
-    buf.push_str(&format!("  \"{k}\": {v}"));
-    if !is_last {
-        buf.push(',');
+fn write_escaped<W: Write>(out: &mut W, s: &str) -> io::Result<()> {
+    out.write_all(b"\"")?;
+
+    let bytes = s.as_bytes();
+    let mut start = 0;
+    for (i, &b) in bytes.iter().enumerate() {
+        let escaped: &[u8] = match b {
+            b'"' => b"\\\"",
+            b'\\' => b"\\\\",
+            b'\n' => b"\\n",
+            b'\r' => b"\\r",
+            b'\t' => b"\\t",
+            0x08 => b"\\b",
+            0x0c => b"\\f",
+            0x00..=0x1f => {
+                out.write_all(&bytes[start..i])?;
+                write!(out, "\\u{b:04x}")?;
+                start = i + 1;
+                continue;
+            }
+            _ => continue,
+        };
+        out.write_all(&bytes[start..i])?;
+        out.write_all(escaped)?;
+        start = i + 1;
     }
-    buf.push('\n');
+    out.write_all(&bytes[start..])?;
+
+    out.write_all(b"\"")
 }
 
-impl Serializer for FileSystemEntry {
+fn to_json_string<F>(write: F) -> String
+where
+    F: FnOnce(&mut JsonWriter<Vec<u8>>) -> io::Result<()>,
+{
+    let mut writer = JsonWriter::new(Vec::new(), true);
+    write(&mut writer).expect("write to Vec");
+    String::from_utf8(writer.into_inner()).expect("JSON output is UTF-8")
+}
+
+/// Optional fields of serialized entries.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct JsonOptions {
+    /// `git_status`, `null` outside of a git repository.
+    pub git_status: bool,
+}
+
+pub trait Serializer {
+    fn write_short_json<W: Write>(
+        &self,
+        writer: &mut JsonWriter<W>,
+        options: JsonOptions,
+    ) -> io::Result<()>;
+    fn write_long_json<W: Write>(
+        &self,
+        writer: &mut JsonWriter<W>,
+        options: JsonOptions,
+    ) -> io::Result<()>;
+    /// Writes a single self-contained object without nested `entries`, as used
+    /// by JSON Lines output where every entry gets its own line.
+    fn write_json_line<W: Write>(
+        &self,
+        writer: &mut JsonWriter<W>,
+        depth: usize,
+        options: JsonOptions,
+    ) -> io::Result<()>;
+
     fn short_json(&self) -> String {
-        match self {
-            FileSystemEntry::File {
-                base_info,
-                metadata,
-                extension,
-            } => {
-                let mut json = String::from("{\n");
-
-                form_field(&mut json, "type", add_quotes("file"), false);
-                form_field(&mut json, "name", add_quotes(&base_info.name), false);
-
-                form_field(&mut json, "size", metadata.size.to_string(), false);
-                form_field(&mut json, "mode", add_quotes(&metadata.mode_str), false);
-                form_field(
-                    &mut json,
-                    "created_at",
-                    add_quotes(&metadata.created_at.format()),
-                    false,
-                );
-                form_field(
-                    &mut json,
-                    "modified_at",
-                    add_quotes(&metadata.modified_at.format()),
-                    false,
-                );
-
-                let extension = add_quotes(if let Some(ext) = extension {
-                    ext
-                } else {
-                    "null"
-                });
-
-                form_field(&mut json, "extension", extension, true);
-
-                json.push('}');
-                json
-            }
-            FileSystemEntry::Directory {
-                base_info,
-                metadata,
-                entries,
-            } => {
-                let mut json = String::from("{\n");
-
-                form_field(&mut json, "type", add_quotes("directory"), false);
-                form_field(&mut json, "name", add_quotes(&base_info.name), false);
-
-                form_field(&mut json, "size", metadata.size.to_string(), false);
-                form_field(&mut json, "mode", add_quotes(&metadata.mode_str), false);
-                form_field(
-                    &mut json,
-                    "created_at",
-                    add_quotes(&metadata.created_at.format()),
-                    false,
-                );
-                form_field(
-                    &mut json,
-                    "modified_at",
-                    add_quotes(&metadata.modified_at.format()),
-                    false,
-                );
-
-                let children_json = entries
-                    .iter()
-                    .map(|e| e.short_json())
-                    .collect::<Vec<_>>()
-                    .join(",\n");
-
-                form_field(&mut json, "entries", format!("[\n{children_json}\n]"), true);
-
-                json.push('}');
-                json
+        to_json_string(|w| self.write_short_json(w, JsonOptions::default()))
+    }
+    fn long_json(&self) -> String {
+        to_json_string(|w| self.write_long_json(w, JsonOptions::default()))
+    }
+}
+
+impl Serializer for FileSystemEntry {
+    fn write_short_json<W: Write>(
+        &self,
+        w: &mut JsonWriter<W>,
+        options: JsonOptions,
+    ) -> io::Result<()> {
+        write_short_entry(w, self, true, options)
+    }
+    fn write_long_json<W: Write>(
+        &self,
+        w: &mut JsonWriter<W>,
+        options: JsonOptions,
+    ) -> io::Result<()> {
+        write_long_entry(w, self, true, options)
+    }
+    fn write_json_line<W: Write>(
+        &self,
+        w: &mut JsonWriter<W>,
+        depth: usize,
+        options: JsonOptions,
+    ) -> io::Result<()> {
+        w.begin_object()?;
+        w.field_uint("schema_version", SCHEMA_VERSION)?;
+        w.field_str("type", self.type_name())?;
+        w.field_uint("depth", depth as u64)?;
+        write_long_fields(w, self, options)?;
+        w.end_object()
+    }
+}
+
+fn write_short_entry<W: Write>(
+    w: &mut JsonWriter<W>,
+    entry: &FileSystemEntry,
+    is_root: bool,
+    options: JsonOptions,
+) -> io::Result<()> {
+    let base_info = entry.base_info();
+    let metadata = entry.metadata();
+
+    w.begin_object()?;
+    if is_root {
+        w.field_uint("schema_version", SCHEMA_VERSION)?;
+    }
+    w.field_str("type", entry.type_name())?;
+    w.field_name("name", &base_info.name)?;
+
+    w.field_uint("size", metadata.size)?;
+    w.field_str("mode", &metadata.mode_str)?;
+    w.field_str("created_at", &metadata.created_at.format())?;
+    w.field_str("modified_at", &metadata.modified_at.format())?;
+
+    match entry {
+        FileSystemEntry::File { extension, .. } => {
+            w.field_opt_str("extension", extension.as_deref())?;
+        }
+        FileSystemEntry::Directory { entries, .. } => {
+            w.key("entries")?;
+            w.begin_array()?;
+            for entry in entries {
+                write_short_entry(w, entry, false, options)?;
             }
-            FileSystemEntry::Link {
-                base_info,
-                metadata,
-                target,
-            } => {
-                let mut json = String::from("{\n");
-
-                form_field(&mut json, "type", add_quotes("directory"), false);
-                form_field(&mut json, "name", add_quotes(&base_info.name), false);
-
-                form_field(&mut json, "size", metadata.size.to_string(), false);
-                form_field(&mut json, "mode", add_quotes(&metadata.mode_str), false);
-                form_field(
-                    &mut json,
-                    "created_at",
-                    add_quotes(&metadata.created_at.format()),
-                    false,
-                );
-                form_field(
-                    &mut json,
-                    "modified_at",
-                    add_quotes(&metadata.modified_at.format()),
-                    false,
-                );
-
-                form_field(
-                    &mut json,
-                    "target",
-                    add_quotes(&target.display().to_string()),
-                    true,
-                );
-
-                json.push('}');
-                json
+            w.end_array()?;
+        }
+        FileSystemEntry::Link { target, .. } => {
+            w.field_name("target", target.as_os_str())?;
+            w.field_bool("broken", entry.is_broken_link())?;
+        }
+        FileSystemEntry::Special { kind, .. } => write_device(w, *kind, metadata)?,
+    }
+    write_git_status(w, entry, options)?;
+
+    w.end_object()
+}
+
+fn write_long_entry<W: Write>(
+    w: &mut JsonWriter<W>,
+    entry: &FileSystemEntry,
+    is_root: bool,
+    options: JsonOptions,
+) -> io::Result<()> {
+    w.begin_object()?;
+    if is_root {
+        w.field_uint("schema_version", SCHEMA_VERSION)?;
+    }
+    w.field_str("type", entry.type_name())?;
+    write_long_fields(w, entry, options)?;
+
+    if let FileSystemEntry::Directory { entries, .. } = entry {
+        w.key("entries")?;
+        w.begin_array()?;
+        for entry in entries {
+            write_long_entry(w, entry, false, options)?;
+        }
+        w.end_array()?;
+    }
+
+    w.end_object()
+}
+
+fn write_time<W: Write>(w: &mut JsonWriter<W>, key: &str, time: &Time) -> io::Result<()> {
+    w.key(key)?;
+    w.begin_object()?;
+    w.field_uint("epoch", time.timestamp())?;
+    w.field_str("iso", &time.to_iso8601())?;
+    w.end_object()
+}
+
+fn write_long_fields<W: Write>(
+    w: &mut JsonWriter<W>,
+    entry: &FileSystemEntry,
+    options: JsonOptions,
+) -> io::Result<()> {
+    let base_info = entry.base_info();
+    let metadata = entry.metadata();
+
+    w.field_name("name", &base_info.name)?;
+    w.field_str("path", &base_info.path.display().to_string())?;
+
+    w.key("style")?;
+    w.begin_object()?;
+    w.field_opt_str(
+        "suffix",
+        base_info.style.suffix.map(|s| s.to_string()).as_deref(),
+    )?;
+    w.field_str("color", base_info.style.color.name())?;
+    w.end_object()?;
+
+    w.field_uint("size", metadata.size)?;
+    w.field_str("human_size", &metadata.human_size)?;
+    #[cfg(unix)]
+    {
+        w.field_uint("inode", metadata.inode)?;
+        w.field_uint("mode", metadata.mode as u64)?;
+        w.field_bool("executable", metadata.executable)?;
+        w.field_uint("uid", metadata.uid as u64)?;
+        w.field_uint("gid", metadata.gid as u64)?;
+        w.field_str("owner", &metadata.owner(false))?;
+        w.field_str("group", &metadata.group(false))?;
+        w.field_uint("nlink", metadata.nlink)?;
+        w.field_uint("blocks", metadata.blocks)?;
+        w.field_uint("blksize", metadata.blksize)?;
+        w.field_uint("allocated_size", metadata.allocated_size())?;
+    }
+    w.field_str("mode_str", &metadata.mode_str)?;
+    write_time(w, "created_at", &metadata.created_at)?;
+    write_time(w, "modified_at", &metadata.modified_at)?;
+
+    match entry {
+        FileSystemEntry::File { extension, .. } => {
+            w.field_opt_str("extension", extension.as_deref())?;
+        }
+        FileSystemEntry::Directory { .. } => {}
+        FileSystemEntry::Link {
+            target, resolved, ..
+        } => {
+            w.field_name("target", target.as_os_str())?;
+            w.field_bool("broken", resolved.is_none())?;
+            w.key("resolved")?;
+            match resolved {
+                Some(resolved) => {
+                    w.begin_object()?;
+                    w.field_str("path", &resolved.base_info().path.display().to_string())?;
+                    w.field_str("type", resolved.type_name())?;
+                    w.end_object()?;
+                }
+                None => w.null()?,
             }
         }
+        FileSystemEntry::Special { kind, .. } => write_device(w, *kind, metadata)?,
     }
-    fn long_json(&self) -> String {
-        String::new()
+    write_git_status(w, entry, options)
+}
+
+fn write_git_status<W: Write>(
+    w: &mut JsonWriter<W>,
+    entry: &FileSystemEntry,
+    options: JsonOptions,
+) -> io::Result<()> {
+    if !options.git_status {
+        return Ok(());
     }
+    w.field_opt_str("git_status", entry.git_status().map(|status| status.name()))
+}
+
+#[cfg(unix)]
+fn write_device<W: Write>(
+    w: &mut JsonWriter<W>,
+    kind: SpecialKind,
+    metadata: &MetaData,
+) -> io::Result<()> {
+    if !kind.is_device() {
+        return Ok(());
+    }
+    let (major, minor) = metadata.device_numbers();
+    w.key("device")?;
+    w.begin_object()?;
+    w.field_uint("major", major as u64)?;
+    w.field_uint("minor", minor as u64)?;
+    w.end_object()
+}
+#[cfg(not(unix))]
+fn write_device<W: Write>(
+    _w: &mut JsonWriter<W>,
+    _kind: SpecialKind,
+    _metadata: &MetaData,
+) -> io::Result<()> {
+    Ok(())
 }
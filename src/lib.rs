@@ -1,7 +1,18 @@
 pub mod command;
 pub mod data_op;
+pub mod diagnostics;
 pub mod error;
 pub mod files;
+pub mod git;
+pub mod gitignore;
 pub mod json;
+pub mod ls_colors;
+pub mod pattern;
+pub mod quoting;
+pub mod sort;
 pub mod term;
+pub mod theme;
 pub mod time;
+#[cfg(unix)]
+pub mod users;
+pub mod width;
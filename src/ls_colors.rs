@@ -0,0 +1,134 @@
+use std::{collections::HashMap, env, sync::OnceLock};
+
+static LS_COLORS: OnceLock<Option<LsColors>> = OnceLock::new();
+
+/// File type keys of the dircolors format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Indicator {
+    Normal,
+    File,
+    Directory,
+    Link,
+    Orphan,
+    Missing,
+    Fifo,
+    Socket,
+    BlockDevice,
+    CharDevice,
+    Executable,
+    Setuid,
+    Setgid,
+    Sticky,
+    OtherWritable,
+    StickyOtherWritable,
+}
+
+impl Indicator {
+    pub fn key(&self) -> &'static str {
+        match self {
+            Indicator::Normal => "no",
+            Indicator::File => "fi",
+            Indicator::Directory => "di",
+            Indicator::Link => "ln",
+            Indicator::Orphan => "or",
+            Indicator::Missing => "mi",
+            Indicator::Fifo => "pi",
+            Indicator::Socket => "so",
+            Indicator::BlockDevice => "bd",
+            Indicator::CharDevice => "cd",
+            Indicator::Executable => "ex",
+            Indicator::Setuid => "su",
+            Indicator::Setgid => "sg",
+            Indicator::Sticky => "st",
+            Indicator::OtherWritable => "ow",
+            Indicator::StickyOtherWritable => "tw",
+        }
+    }
+}
+
+/// Parsed `LS_COLORS` value: SGR parameters (e.g. `01;34`) per type key and per
+/// `*suffix` pattern.
+#[derive(Debug, Clone, Default)]
+pub struct LsColors {
+    indicators: HashMap<String, String>,
+    // in definition order, later patterns win
+    suffixes: Vec<(String, String)>,
+}
+
+impl LsColors {
+    pub fn parse(spec: &str) -> Self {
+        let mut colors = LsColors::default();
+
+        for item in spec.split(':') {
+            let Some((key, value)) = item.split_once('=') else {
+                continue;
+            };
+            // a bare `*` would match every name
+            if key.is_empty() || key == "*" || value.is_empty() {
+                continue;
+            }
+
+            if let Some(suffix) = key.strip_prefix('*') {
+                colors
+                    .suffixes
+                    .push((suffix.to_string(), value.to_string()));
+            } else {
+                colors.indicators.insert(key.to_string(), value.to_string());
+            }
+        }
+
+        colors
+    }
+    pub fn from_env() -> Option<Self> {
+        let spec = env::var("LS_COLORS").ok()?;
+        if spec.is_empty() {
+            return None;
+        }
+        Some(Self::parse(&spec))
+    }
+    /// Installs the palette returned by [`LsColors::global`]; only the first
+    /// call has an effect. The binary installs [`LsColors::from_env`].
+    pub fn init(colors: Option<LsColors>) {
+        let _ = LS_COLORS.set(colors);
+    }
+    /// Palette installed by [`LsColors::init`], none if it was never called.
+    pub fn global() -> Option<&'static LsColors> {
+        LS_COLORS.get()?.as_ref()
+    }
+    pub fn indicator(&self, indicator: Indicator) -> Option<&str> {
+        self.indicators.get(indicator.key()).map(|s| s.as_str())
+    }
+    /// Looks up the `*suffix` pattern matching `name`, preferring an exact-case
+    /// match over a case-insensitive one.
+    pub fn suffix(&self, name: &str) -> Option<&str> {
+        let exact = self
+            .suffixes
+            .iter()
+            .rev()
+            .find(|(suffix, _)| name.ends_with(suffix.as_str()));
+        if let Some((_, value)) = exact {
+            return Some(value);
+        }
+
+        let lower = name.to_lowercase();
+        self.suffixes
+            .iter()
+            .rev()
+            .find(|(suffix, _)| lower.ends_with(&suffix.to_lowercase()))
+            .map(|(_, value)| value.as_str())
+    }
+    /// Picks the colour of an entry: the first defined indicator of `specific`,
+    /// then the suffix pattern of `name` (if given), then `base`.
+    pub fn pick(
+        &self,
+        specific: &[Indicator],
+        name: Option<&str>,
+        base: Indicator,
+    ) -> Option<&str> {
+        specific
+            .iter()
+            .find_map(|&indicator| self.indicator(indicator))
+            .or_else(|| name.and_then(|name| self.suffix(name)))
+            .or_else(|| self.indicator(base))
+    }
+}
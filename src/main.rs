@@ -1,18 +1,48 @@
-use ls_rs::{command, data_op::Printer, error::LsResult, files::FileSystemEntry};
+use std::process::ExitCode;
 
-fn main() -> LsResult<()> {
+use ls_rs::{
+    command,
+    data_op::Printer,
+    diagnostics::{Diagnostic, Diagnostics, Operation, Severity},
+    files::FileSystemEntry,
+    json,
+    ls_colors::LsColors,
+    theme::Theme,
+};
+
+fn main() -> ExitCode {
     let matches = command::ls_command().get_matches();
     let config = command::Config::clap_parse(&matches);
 
-    let start_dir = match FileSystemEntry::new_with_config(&config) {
-        Ok(dir) => dir,
-        Err(e) => {
-            eprintln!("{e}");
-            return Err(e);
+    if config.json_schema {
+        print!("{}", json::SCHEMA);
+        return ExitCode::SUCCESS;
+    }
+
+    LsColors::init(LsColors::from_env());
+    Theme::init(Theme::load());
+    let diagnostics = Diagnostics::printing();
+    let mut operands = Vec::with_capacity(config.paths.len());
+    for path in &config.paths {
+        // JSON Lines output reads the directory itself while printing
+        let operand = if config.json_lines {
+            FileSystemEntry::root_for_path(path, &config)
+        } else {
+            FileSystemEntry::new_for_path(path, &config, &diagnostics)
+        };
+        match operand {
+            Ok(fse) => operands.push((path.clone(), fse)),
+            Err(e) => diagnostics.record(Diagnostic::new(
+                Operation::Access,
+                path,
+                e,
+                Severity::Serious,
+            )),
         }
-    };
+    }
 
-    let printer = Printer::new(start_dir, config);
-    printer.print();
-    Ok(())
+    if !operands.is_empty() {
+        Printer::with_operands(operands, config, &diagnostics).print();
+    }
+    ExitCode::from(diagnostics.exit_code())
 }
@@ -0,0 +1,192 @@
+use std::{ffi::OsStr, path::Path};
+
+use regex::Regex;
+
+/// A pattern of `-I`/`--ignore` or `--hide`: a shell glob, or with `--regex`
+/// a regular expression that has to match the whole name.
+#[derive(Debug, Clone)]
+pub enum Pattern {
+    Glob(Glob),
+    Regex(Regex),
+}
+
+impl Pattern {
+    pub fn new(pattern: &str, regex: bool) -> Result<Self, regex::Error> {
+        if regex {
+            Regex::new(&format!("^(?:{pattern})$")).map(Pattern::Regex)
+        } else {
+            Ok(Pattern::Glob(Glob::new(pattern)))
+        }
+    }
+    /// Whether the entry `name` at `path` matches; globs with a `/` are matched
+    /// against the trailing components of the path instead of the name.
+    pub fn matches(&self, name: &OsStr, path: &Path) -> bool {
+        match self {
+            Pattern::Glob(glob) => glob.matches(name, path),
+            Pattern::Regex(regex) => regex.is_match(&name.to_string_lossy()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Char(char),
+    // `?`
+    Any,
+    // `*`, within one path component
+    Star,
+    // `**`, across components
+    DoubleStar,
+    // `**/`, any number of leading directories, including none
+    AnyDirs,
+    // `[a-z]`, `[!abc]`
+    Class {
+        negated: bool,
+        ranges: Vec<(char, char)>,
+    },
+}
+
+/// A shell glob with `*`, `?`, `[...]` and `**`. Like `fnmatch` with
+/// `FNM_PERIOD`, a leading `.` of a name has to be matched explicitly.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Glob {
+    tokens: Vec<Token>,
+    // matched against paths rather than names
+    has_slash: bool,
+}
+
+impl Glob {
+    pub fn new(pattern: &str) -> Self {
+        let chars: Vec<char> = pattern.chars().collect();
+        let mut tokens = Vec::new();
+        let mut i = 0;
+        while i < chars.len() {
+            match chars[i] {
+                '\\' if i + 1 < chars.len() => {
+                    tokens.push(Token::Char(chars[i + 1]));
+                    i += 1;
+                }
+                '*' if chars.get(i + 1) == Some(&'*') => {
+                    if chars.get(i + 2) == Some(&'/') {
+                        tokens.push(Token::AnyDirs);
+                        i += 2;
+                    } else {
+                        tokens.push(Token::DoubleStar);
+                        i += 1;
+                    }
+                }
+                '*' => tokens.push(Token::Star),
+                '?' => tokens.push(Token::Any),
+                '[' => match parse_class(&chars[i + 1..]) {
+                    Some((token, len)) => {
+                        tokens.push(token);
+                        i += len;
+                    }
+                    // an unclosed `[` is an ordinary character
+                    None => tokens.push(Token::Char('[')),
+                },
+                c => tokens.push(Token::Char(c)),
+            }
+            i += 1;
+        }
+        Self {
+            has_slash: pattern.contains('/'),
+            tokens,
+        }
+    }
+    pub fn matches(&self, name: &OsStr, path: &Path) -> bool {
+        if !self.has_slash {
+            return self.matches_name(&name.to_string_lossy());
+        }
+        let path: Vec<char> = path.to_string_lossy().chars().collect();
+        // any trailing run of whole components
+        (0..path.len())
+            .filter(|&i| i == 0 || path[i - 1] == '/')
+            .any(|i| match_tokens(&self.tokens, &path[i..]))
+    }
+    /// Whether the glob matches all of `text`, `/` separated components
+    /// included, with no special treatment of leading dots.
+    pub fn matches_str(&self, text: &str) -> bool {
+        let text: Vec<char> = text.chars().collect();
+        match_tokens(&self.tokens, &text)
+    }
+    fn matches_name(&self, name: &str) -> bool {
+        if name.starts_with('.') && self.tokens.first() != Some(&Token::Char('.')) {
+            return false;
+        }
+        let name: Vec<char> = name.chars().collect();
+        match_tokens(&self.tokens, &name)
+    }
+}
+
+// `[...]` after the `[`, and the number of characters it takes.
+fn parse_class(chars: &[char]) -> Option<(Token, usize)> {
+    let negated = matches!(chars.first(), Some('!' | '^'));
+    let mut i = usize::from(negated);
+    let mut ranges = Vec::new();
+    // a `]` right at the start is part of the class
+    let start = i;
+    while i < chars.len() {
+        let c = chars[i];
+        if c == ']' && i > start {
+            return Some((Token::Class { negated, ranges }, i + 1));
+        }
+        if chars.get(i + 1) == Some(&'-') && chars.get(i + 2).is_some_and(|&end| end != ']') {
+            ranges.push((c, chars[i + 2]));
+            i += 3;
+        } else {
+            ranges.push((c, c));
+            i += 1;
+        }
+    }
+    None
+}
+
+// Whether `tokens` match all of `text`. Every (token, position) pair is tried
+// at most once, so patterns with many stars stay polynomial.
+fn match_tokens(tokens: &[Token], text: &[char]) -> bool {
+    let mut failed = vec![false; (tokens.len() + 1) * (text.len() + 1)];
+    match_from(tokens, text, 0, 0, &mut failed)
+}
+
+fn match_from(tokens: &[Token], text: &[char], t: usize, i: usize, failed: &mut [bool]) -> bool {
+    let key = t * (text.len() + 1) + i;
+    if failed[key] {
+        return false;
+    }
+    let mut rest = |j: usize| match_from(tokens, text, t + 1, j, failed);
+    let matched = match tokens.get(t) {
+        None => i == text.len(),
+        Some(Token::Star) => {
+            let mut matched = false;
+            for j in i..=text.len() {
+                if rest(j) {
+                    matched = true;
+                    break;
+                }
+                if text.get(j) == Some(&'/') {
+                    break;
+                }
+            }
+            matched
+        }
+        Some(Token::DoubleStar) => (i..=text.len()).any(rest),
+        Some(Token::AnyDirs) => (i..=text.len())
+            .filter(|&j| j == i || text[j - 1] == '/')
+            .any(rest),
+        Some(Token::Any) => text.get(i).is_some_and(|&c| c != '/') && rest(i + 1),
+        Some(Token::Char(c)) => text.get(i) == Some(c) && rest(i + 1),
+        Some(Token::Class { negated, ranges }) => {
+            text.get(i).is_some_and(|&c| {
+                let inside = ranges
+                    .iter()
+                    .any(|&(start, end)| (start..=end).contains(&c));
+                c != '/' && inside != *negated
+            }) && rest(i + 1)
+        }
+    };
+    if !matched {
+        failed[key] = true;
+    }
+    matched
+}
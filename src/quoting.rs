@@ -0,0 +1,200 @@
+use std::{env, ffi::OsStr};
+
+/// How file names are quoted, as in GNU ls `--quoting-style`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum QuotingStyle {
+    // names as they are
+    #[default]
+    Literal,
+    // quoted for the shell when needed, control characters as `?`
+    Shell,
+    // quoted for the shell when needed, control characters as `$'\n'`
+    ShellEscape,
+    // C string literal in double quotes
+    C,
+    // like `c` without the quotes, spaces escaped
+    Escape,
+}
+
+impl QuotingStyle {
+    pub const NAMES: [&'static str; 5] = ["literal", "shell", "shell-escape", "c", "escape"];
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "literal" => Some(QuotingStyle::Literal),
+            "shell" => Some(QuotingStyle::Shell),
+            "shell-escape" => Some(QuotingStyle::ShellEscape),
+            "c" => Some(QuotingStyle::C),
+            "escape" => Some(QuotingStyle::Escape),
+            _ => None,
+        }
+    }
+    /// Style named by `QUOTING_STYLE`, if it names a known one.
+    pub fn from_env() -> Option<Self> {
+        Self::parse(&env::var("QUOTING_STYLE").ok()?)
+    }
+}
+
+/// Quoting of the names of one listing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Quoting {
+    pub style: QuotingStyle,
+    // `-q`: print control characters (and invalid bytes) of literal names as `?`
+    pub hide_control: bool,
+}
+
+// A decoded piece of a file name: a character, or a byte that is not valid UTF-8.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Piece {
+    Char(char),
+    Byte(u8),
+}
+
+impl Piece {
+    fn is_printable(&self) -> bool {
+        matches!(self, Piece::Char(c) if !c.is_control())
+    }
+}
+
+#[cfg(unix)]
+fn pieces(name: &OsStr) -> Vec<Piece> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let mut pieces = Vec::new();
+    for chunk in name.as_bytes().utf8_chunks() {
+        pieces.extend(chunk.valid().chars().map(Piece::Char));
+        pieces.extend(chunk.invalid().iter().map(|&b| Piece::Byte(b)));
+    }
+    pieces
+}
+
+#[cfg(not(unix))]
+fn pieces(name: &OsStr) -> Vec<Piece> {
+    name.to_string_lossy().chars().map(Piece::Char).collect()
+}
+
+// Characters that make the shell styles quote a name.
+const SHELL_SPECIAL: &str = " \t\n\"$&'()*;<>?[\\]^`{|}!";
+
+impl Quoting {
+    pub fn new(style: QuotingStyle, hide_control: bool) -> Self {
+        Self {
+            style,
+            hide_control,
+        }
+    }
+    /// Renders `name` in the quoting style; names that are not valid UTF-8 are
+    /// decoded lossily by the styles that do not escape bytes.
+    pub fn quote(&self, name: &OsStr) -> String {
+        let pieces = pieces(name);
+        match self.style {
+            QuotingStyle::Literal => literal(&pieces, self.hide_control),
+            QuotingStyle::Shell => shell(&pieces, false),
+            QuotingStyle::ShellEscape => shell(&pieces, true),
+            QuotingStyle::C => format!("\"{}\"", escape(&pieces, true)),
+            QuotingStyle::Escape => escape(&pieces, false),
+        }
+    }
+}
+
+fn literal(pieces: &[Piece], hide_control: bool) -> String {
+    pieces
+        .iter()
+        .map(|piece| match piece {
+            _ if hide_control && !piece.is_printable() => '?',
+            Piece::Char(c) => *c,
+            Piece::Byte(_) => char::REPLACEMENT_CHARACTER,
+        })
+        .collect()
+}
+
+// Backslash escapes of the `c` and `escape` styles.
+fn escape(pieces: &[Piece], c_style: bool) -> String {
+    let mut out = String::new();
+    for piece in pieces {
+        match *piece {
+            Piece::Char('\\') => out.push_str("\\\\"),
+            Piece::Char('"') if c_style => out.push_str("\\\""),
+            Piece::Char(' ') if !c_style => out.push_str("\\ "),
+            Piece::Char(c) if !c.is_control() => out.push(c),
+            Piece::Char(c) => push_control_escape(&mut out, c),
+            Piece::Byte(b) => out.push_str(&format!("\\{b:03o}")),
+        }
+    }
+    out
+}
+
+fn push_control_escape(out: &mut String, c: char) {
+    match c {
+        '\x07' => out.push_str("\\a"),
+        '\x08' => out.push_str("\\b"),
+        '\x0c' => out.push_str("\\f"),
+        '\n' => out.push_str("\\n"),
+        '\r' => out.push_str("\\r"),
+        '\t' => out.push_str("\\t"),
+        '\x0b' => out.push_str("\\v"),
+        c => {
+            let mut buf = [0; 4];
+            for b in c.encode_utf8(&mut buf).bytes() {
+                out.push_str(&format!("\\{b:03o}"));
+            }
+        }
+    }
+}
+
+// `shell` and `shell-escape` styles: names are left alone unless they contain
+// something special to the shell; then they are single quoted, or double
+// quoted if that avoids escaping a single quote.
+fn shell(pieces: &[Piece], escape_control: bool) -> String {
+    let printable = pieces.iter().all(Piece::is_printable);
+    let needs_quotes = pieces.is_empty()
+        || matches!(pieces.first(), Some(Piece::Char('~' | '#')))
+        || pieces
+            .iter()
+            .any(|p| matches!(p, Piece::Char(c) if SHELL_SPECIAL.contains(*c)))
+        // control characters become `?` or `$'...'`, both need quoting
+        || !printable;
+
+    if !needs_quotes {
+        return literal(pieces, true);
+    }
+
+    let has_single_quote = pieces.contains(&Piece::Char('\''));
+    let double_quote_safe = pieces
+        .iter()
+        .all(|p| !matches!(p, Piece::Char('"' | '$' | '`' | '\\' | '!')));
+    if has_single_quote && double_quote_safe && (printable || !escape_control) {
+        return format!("\"{}\"", literal(pieces, true));
+    }
+
+    let mut out = String::from("'");
+    let mut i = 0;
+    while i < pieces.len() {
+        match pieces[i] {
+            Piece::Char('\'') => out.push_str("'\\''"),
+            piece if piece.is_printable() => {
+                if let Piece::Char(c) = piece {
+                    out.push(c);
+                }
+            }
+            _ if escape_control => {
+                // a run of unprintable pieces becomes one `$'...'` word
+                let end = pieces[i..]
+                    .iter()
+                    .position(Piece::is_printable)
+                    .map_or(pieces.len(), |n| i + n);
+                out.push_str(&format!("'$'{}''", escape(&pieces[i..end], true)));
+                i = end;
+                continue;
+            }
+            _ => out.push('?'),
+        }
+        i += 1;
+    }
+    out.push('\'');
+
+    // drop the empty quotes around a leading or trailing `$'...'`
+    let out = out.strip_prefix("''").unwrap_or(&out);
+    let out = out.strip_suffix("''").unwrap_or(out);
+    out.to_string()
+}
@@ -0,0 +1,138 @@
+use std::{cmp::Ordering, path::Path};
+
+use crate::{
+    command::{Config, SortType},
+    files::{FileSystemEntry, FileType},
+    width,
+};
+
+/// How listings order the entries of a directory: by the sort keys, reversed
+/// with `-r`, and with `--group-directories-first` directories (and links to
+/// them) before everything else, in either direction.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Order<'a> {
+    keys: &'a [SortType],
+    reverse: bool,
+    directories_first: bool,
+}
+
+impl<'a> Order<'a> {
+    pub fn new(config: &'a Config) -> Self {
+        Self {
+            keys: &config.sort_keys,
+            reverse: config.reverse,
+            directories_first: config.group_directories_first,
+        }
+    }
+    /// The order of `config`, `None` if it leaves entries in directory order,
+    /// so walks can stream them as read.
+    pub fn requested(config: &'a Config) -> Option<Self> {
+        let order = Self::new(config);
+        (!order.keys.is_empty() || order.reverse || order.directories_first).then_some(order)
+    }
+    pub fn apply(&self, entries: &mut [FileSystemEntry]) {
+        if !self.keys.is_empty() {
+            entries.sort_by(|a, b| compare(self.keys, a, b));
+        }
+        if self.reverse {
+            entries.reverse();
+        }
+        if self.directories_first {
+            // stable, both groups keep their order
+            entries.sort_by_key(|fse| !lists_as_directory(fse));
+        }
+    }
+}
+
+/// Orders two entries by `keys`, each breaking the ties of the ones before;
+/// entries equal by all of them are ordered by name, so listings do not
+/// depend on the order the directory was read in.
+pub fn compare(keys: &[SortType], a: &FileSystemEntry, b: &FileSystemEntry) -> Ordering {
+    keys.iter()
+        .fold(Ordering::Equal, |ordering, key| {
+            ordering.then_with(|| compare_key(key, a, b))
+        })
+        .then_with(|| a.name().cmp(&b.name()))
+}
+
+fn lists_as_directory(fse: &FileSystemEntry) -> bool {
+    match fse {
+        FileSystemEntry::Link {
+            resolved: Some(resolved),
+            ..
+        } => resolved.is_dir(),
+        fse => fse.is_dir(),
+    }
+}
+
+fn type_rank(fse: &FileSystemEntry) -> u8 {
+    match FileType::from(fse) {
+        FileType::Directory => 0,
+        FileType::File => 1,
+        FileType::Link => 2,
+        FileType::Special => 3,
+    }
+}
+
+fn compare_key(key: &SortType, a: &FileSystemEntry, b: &FileSystemEntry) -> Ordering {
+    let (ma, mb) = (a.metadata(), b.metadata());
+    match key {
+        SortType::Name => a.name().cmp(&b.name()),
+        SortType::Type => type_rank(a).cmp(&type_rank(b)),
+        SortType::Version => version_cmp(&a.name(), &b.name()),
+        SortType::Extension => extension(a).cmp(&extension(b)),
+        SortType::Size => ma.size.cmp(&mb.size),
+        SortType::Allocated => ma.allocated_size().cmp(&mb.allocated_size()),
+        SortType::Time => ma.modified_at.cmp(&mb.modified_at),
+        SortType::Created => ma.created_at.cmp(&mb.created_at),
+        #[cfg(unix)]
+        SortType::Changed => ma.changed_at.cmp(&mb.changed_at),
+        #[cfg(windows)]
+        SortType::Changed => ma.modified_at.cmp(&mb.modified_at),
+        SortType::Accessed => ma.accessed_at.cmp(&mb.accessed_at),
+        #[cfg(unix)]
+        SortType::Inode => ma.inode.cmp(&mb.inode),
+        #[cfg(windows)]
+        SortType::Inode => Ordering::Equal,
+        SortType::Width => width::display_width(&a.name()).cmp(&width::display_width(&b.name())),
+    }
+}
+
+// Names without an extension come first, like with `ls -X`.
+fn extension(fse: &FileSystemEntry) -> Option<String> {
+    Path::new(fse.os_name())
+        .extension()
+        .map(|ext| ext.to_string_lossy().into_owned())
+}
+
+/// Natural order of names: runs of digits compare by their numeric value, so
+/// `file2` comes before `file10` and `v1.9` before `v1.10`.
+pub fn version_cmp(a: &str, b: &str) -> Ordering {
+    let (mut a, mut b) = (a, b);
+    loop {
+        let (Some(ca), Some(cb)) = (a.chars().next(), b.chars().next()) else {
+            return a.len().cmp(&b.len());
+        };
+        let ordering = if ca.is_ascii_digit() && cb.is_ascii_digit() {
+            let (da, rest_a) = split_digits(a);
+            let (db, rest_b) = split_digits(b);
+            a = rest_a;
+            b = rest_b;
+            // leading zeros do not change the value
+            let (da, db) = (da.trim_start_matches('0'), db.trim_start_matches('0'));
+            da.len().cmp(&db.len()).then_with(|| da.cmp(db))
+        } else {
+            a = &a[ca.len_utf8()..];
+            b = &b[cb.len_utf8()..];
+            ca.cmp(&cb)
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+}
+
+fn split_digits(s: &str) -> (&str, &str) {
+    let end = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    s.split_at(end)
+}
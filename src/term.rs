@@ -50,3 +50,10 @@ pub fn terminal_size() -> Option<(u16, u16)> {
 pub fn terminal_size() -> Option<(u16, u16)> {
     None // Unsupported platforms
 }
+
+/// Whether stdout (the stream `terminal_size` probes) is attached to a terminal.
+pub fn stdout_is_terminal() -> bool {
+    use std::io::{self, IsTerminal};
+
+    io::stdout().is_terminal()
+}
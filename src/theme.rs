@@ -0,0 +1,272 @@
+use std::{
+    collections::HashMap,
+    env, fs,
+    path::PathBuf,
+    sync::OnceLock,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::time::Time;
+
+static THEME: OnceLock<Option<Theme>> = OnceLock::new();
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Color {
+    // 0..=7 normal, 8..=15 bright
+    Basic(u8),
+    Indexed(u8),
+    Rgb(u8, u8, u8),
+}
+
+impl Color {
+    const NAMES: [&'static str; 8] = [
+        "black", "red", "green", "yellow", "blue", "magenta", "cyan", "white",
+    ];
+
+    pub fn parse(s: &str) -> Option<Self> {
+        if let Some(hex) = s.strip_prefix('#') {
+            if hex.len() != 6 || !hex.is_ascii() {
+                return None;
+            }
+            let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+            return Some(Color::Rgb(channel(0)?, channel(2)?, channel(4)?));
+        }
+        if let Ok(index) = s.parse::<u8>() {
+            return Some(Color::Indexed(index));
+        }
+
+        let (name, bright) = match s.strip_prefix("bright_") {
+            Some(name) => (name, true),
+            None => (s, false),
+        };
+        let base = Self::NAMES.iter().position(|&n| n == name)? as u8;
+        Some(Color::Basic(if bright { base + 8 } else { base }))
+    }
+    fn sgr(&self, background: bool) -> String {
+        match *self {
+            Color::Basic(n) => {
+                let base = match (background, n >= 8) {
+                    (false, false) => 30,
+                    (false, true) => 90 - 8,
+                    (true, false) => 40,
+                    (true, true) => 100 - 8,
+                };
+                (base + n as u16).to_string()
+            }
+            Color::Indexed(n) => format!("{};5;{n}", if background { 48 } else { 38 }),
+            Color::Rgb(r, g, b) => format!("{};2;{r};{g};{b}", if background { 48 } else { 38 }),
+        }
+    }
+}
+
+/// Colour and text attributes of one themed element, e.g. `bold #ff8800 on_black`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Style {
+    pub fg: Option<Color>,
+    pub bg: Option<Color>,
+    pub bold: bool,
+    pub dim: bool,
+    pub italic: bool,
+    pub underline: bool,
+}
+
+impl Style {
+    /// Parses space separated tokens: attributes (`bold`, `dim`, `italic`,
+    /// `underline`), a foreground colour and an `on_`-prefixed background colour.
+    /// Colours are names (`blue`, `bright_red`), 256-colour indexes or `#rrggbb`.
+    pub fn parse(s: &str) -> Option<Self> {
+        let mut style = Style::default();
+        for token in s.split_whitespace() {
+            match token {
+                "bold" => style.bold = true,
+                "dim" => style.dim = true,
+                "italic" => style.italic = true,
+                "underline" => style.underline = true,
+                _ => {
+                    if let Some(bg) = token.strip_prefix("on_") {
+                        style.bg = Some(Color::parse(bg)?);
+                    } else {
+                        style.fg = Some(Color::parse(token)?);
+                    }
+                }
+            }
+        }
+        Some(style)
+    }
+    /// SGR parameters of the style, e.g. `1;38;5;208`.
+    pub fn sgr(&self) -> String {
+        let mut params = Vec::new();
+        for (set, code) in [
+            (self.bold, "1"),
+            (self.dim, "2"),
+            (self.italic, "3"),
+            (self.underline, "4"),
+        ] {
+            if set {
+                params.push(code.to_string());
+            }
+        }
+        if let Some(fg) = self.fg {
+            params.push(fg.sgr(false));
+        }
+        if let Some(bg) = self.bg {
+            params.push(bg.sgr(true));
+        }
+        params.join(";")
+    }
+    pub fn paint<S: AsRef<str>>(&self, s: S) -> String {
+        format!("\x1b[{}m{}\x1b[0m", self.sgr(), s.as_ref())
+    }
+}
+
+/// User theme loaded from `$FLS_THEME` or `<config dir>/fls/theme.toml`.
+///
+/// The file is a flat subset of TOML (or plain `key=value` lines); a `[section]`
+/// header prefixes the following keys with `section.`. Known keys:
+///
+/// - entries: `file`, `directory`, `link`, `broken_link`, `executable`, `socket`,
+///   `special` (fifos and devices), `ext.<extension>` (the longest matching
+///   suffix wins, so `ext.tar.gz` beats `ext.gz`)
+/// - long format: `perm.type`, `perm.read`, `perm.write`, `perm.exec`, `perm.none`,
+///   `size.bytes`, `size.kilo`, `size.mega`, `size.giga`,
+///   `date.hour`, `date.day`, `date.week`, `date.old`, `owner`, `group`
+/// - `--git` column: `git.unmodified`, `git.new`, `git.modified`, `git.staged`,
+///   `git.ignored`, `git.conflicted`
+#[derive(Debug, Clone, Default)]
+pub struct Theme {
+    styles: HashMap<String, Style>,
+}
+
+impl Theme {
+    pub fn parse(content: &str) -> Self {
+        let mut styles = HashMap::new();
+        let mut section = String::new();
+
+        for line in content.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                section = unquote(name.trim()).to_string();
+                continue;
+            }
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+
+            let key = unquote(key.trim());
+            let key = if section.is_empty() {
+                key.to_string()
+            } else {
+                format!("{section}.{key}")
+            };
+            // trailing comments are only allowed after a quoted value
+            let value = value.trim();
+            let value = match value.strip_prefix('"') {
+                Some(rest) => rest.split('"').next().unwrap_or_default(),
+                None => value,
+            };
+
+            if let Some(style) = Style::parse(value) {
+                styles.insert(key, style);
+            }
+        }
+
+        Self { styles }
+    }
+    pub fn config_path() -> Option<PathBuf> {
+        if let Some(path) = env::var_os("FLS_THEME") {
+            return Some(PathBuf::from(path));
+        }
+
+        let config_dir = env::var_os("XDG_CONFIG_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+            .or_else(|| env::var_os("APPDATA").map(PathBuf::from))?;
+
+        ["theme.toml", "theme"]
+            .iter()
+            .map(|name| config_dir.join("fls").join(name))
+            .find(|path| path.is_file())
+    }
+    pub fn load() -> Option<Self> {
+        let content = fs::read_to_string(Self::config_path()?).ok()?;
+        Some(Self::parse(&content))
+    }
+    /// Installs the theme returned by [`Theme::global`]; only the first call
+    /// has an effect. The binary installs [`Theme::load`].
+    pub fn init(theme: Option<Theme>) {
+        let _ = THEME.set(theme);
+    }
+    /// Theme installed by [`Theme::init`], none if it was never called.
+    pub fn global() -> Option<&'static Theme> {
+        THEME.get()?.as_ref()
+    }
+    pub fn get(&self, key: &str) -> Option<&Style> {
+        self.styles.get(key)
+    }
+    /// Style of the longest `ext.` key that `name` ends with after a dot, e.g.
+    /// `ext.tar.gz` before `ext.gz` for `backup.tar.gz`. A leading dot does not
+    /// start an extension.
+    pub fn extension(&self, name: &str) -> Option<&Style> {
+        name.match_indices('.')
+            .filter(|&(i, _)| i > 0)
+            .find_map(|(i, _)| self.get(&format!("ext.{}", &name[i + 1..])))
+    }
+    /// Paints `s` with the style of `key`, leaving it untouched if the key is not themed.
+    pub fn paint(&self, key: &str, s: &str) -> String {
+        match self.get(key) {
+            Some(style) => style.paint(s),
+            None => s.to_string(),
+        }
+    }
+    /// Colours every permission character of a mode string like `drwxr-x---`.
+    pub fn paint_mode(&self, mode_str: &str) -> String {
+        mode_str
+            .chars()
+            .enumerate()
+            .map(|(i, c)| {
+                let key = match c {
+                    _ if i == 0 => "perm.type",
+                    'r' => "perm.read",
+                    'w' => "perm.write",
+                    'x' | 's' | 't' | 'S' | 'T' => "perm.exec",
+                    _ => "perm.none",
+                };
+                self.paint(key, &c.to_string())
+            })
+            .collect()
+    }
+    /// Colours an already formatted size by the magnitude of `size`.
+    pub fn paint_size(&self, text: &str, size: u64) -> String {
+        let key = match size {
+            0..1024 => "size.bytes",
+            1024..1048576 => "size.kilo",
+            1048576..1073741824 => "size.mega",
+            _ => "size.giga",
+        };
+        self.paint(key, text)
+    }
+    /// Colours an already formatted date by how long ago `time` was.
+    pub fn paint_date(&self, text: &str, time: &Time) -> String {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        let key = match now.saturating_sub(time.timestamp()) {
+            0..3600 => "date.hour",
+            3600..86400 => "date.day",
+            86400..604800 => "date.week",
+            _ => "date.old",
+        };
+        self.paint(key, text)
+    }
+}
+
+fn unquote(s: &str) -> &str {
+    s.strip_prefix('"')
+        .and_then(|s| s.strip_suffix('"'))
+        .unwrap_or(s)
+}
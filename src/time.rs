@@ -103,9 +103,14 @@ impl Time {
     fn secs(&self) -> u64 {
         self.duration_since_epoch.as_secs()
     }
+    fn local_secs(&self) -> i64 {
+        self.secs() as i64 + self.offset
+    }
+    pub fn timestamp(&self) -> u64 {
+        self.secs()
+    }
     fn to_calendar_date(&self) -> (i32, u32, u32) {
-        let secs = self.secs() + self.offset as u64;
-        let mut days = secs as i32 / 86400;
+        let mut days = self.local_secs().div_euclid(86400) as i32;
         // let rem_secs = secs % 86400;
 
         let mut year = 1970;
@@ -141,7 +146,7 @@ impl Time {
         (year, month, day)
     }
     fn to_time_parts(&self) -> (u32, u32, u32) {
-        let secs = (self.secs() as u32 + self.offset as u32) % 86400;
+        let secs = self.local_secs().rem_euclid(86400) as u32;
 
         let hours = secs / 3600;
         let minutes = (secs % 3600) / 60;
@@ -161,4 +166,17 @@ impl Time {
 
         format!("{month_str} {day:>2} {hours:02}:{minutes:02}")
     }
+    pub fn to_iso8601(&self) -> String {
+        let (year, month, day) = self.to_calendar_date();
+        let (hours, minutes, seconds) = self.to_time_parts();
+
+        let sign = if self.offset < 0 { '-' } else { '+' };
+        let offset = self.offset.abs();
+
+        format!(
+            "{year:04}-{month:02}-{day:02}T{hours:02}:{minutes:02}:{seconds:02}{sign}{:02}:{:02}",
+            offset / 3600,
+            (offset % 3600) / 60
+        )
+    }
 }
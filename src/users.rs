@@ -0,0 +1,72 @@
+use std::{
+    collections::HashMap,
+    ffi::CStr,
+    mem::MaybeUninit,
+    ptr,
+    sync::{Mutex, OnceLock},
+};
+
+// a typical `_SC_GETPW_R_SIZE_MAX`; grown on ERANGE
+const INITIAL_BUF_SIZE: usize = 1024;
+const MAX_BUF_SIZE: usize = 1 << 20;
+
+/// Name of the user `uid` from the passwd database, cached for the whole run.
+pub fn user_name(uid: u32) -> Option<String> {
+    static USERS: OnceLock<Mutex<HashMap<u32, Option<String>>>> = OnceLock::new();
+    cached(&USERS, uid, lookup_user)
+}
+
+/// Name of the group `gid` from the group database, cached for the whole run.
+pub fn group_name(gid: u32) -> Option<String> {
+    static GROUPS: OnceLock<Mutex<HashMap<u32, Option<String>>>> = OnceLock::new();
+    cached(&GROUPS, gid, lookup_group)
+}
+
+fn cached(
+    cache: &OnceLock<Mutex<HashMap<u32, Option<String>>>>,
+    id: u32,
+    lookup: fn(u32) -> Option<String>,
+) -> Option<String> {
+    let mut cache = cache
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap_or_else(|e| e.into_inner());
+    cache.entry(id).or_insert_with(|| lookup(id)).clone()
+}
+
+fn lookup_user(uid: u32) -> Option<String> {
+    let mut pwd = MaybeUninit::<libc::passwd>::uninit();
+    with_buffer(|buf, result: &mut *mut libc::passwd| unsafe {
+        let code = libc::getpwuid_r(uid, pwd.as_mut_ptr(), buf.as_mut_ptr(), buf.len(), result);
+        (code, result.as_ref().map(|pwd| pwd.pw_name))
+    })
+}
+
+fn lookup_group(gid: u32) -> Option<String> {
+    let mut grp = MaybeUninit::<libc::group>::uninit();
+    with_buffer(|buf, result: &mut *mut libc::group| unsafe {
+        let code = libc::getgrgid_r(gid, grp.as_mut_ptr(), buf.as_mut_ptr(), buf.len(), result);
+        (code, result.as_ref().map(|grp| grp.gr_name))
+    })
+}
+
+/// Runs a reentrant `get*_r` call, growing its string buffer until it fits,
+/// and copies out the name it returned.
+fn with_buffer<T>(
+    mut call: impl FnMut(&mut [libc::c_char], &mut *mut T) -> (libc::c_int, Option<*mut libc::c_char>),
+) -> Option<String> {
+    let mut buf = vec![0 as libc::c_char; INITIAL_BUF_SIZE];
+    loop {
+        let mut result = ptr::null_mut();
+        match call(&mut buf, &mut result) {
+            (0, Some(name)) if !name.is_null() => {
+                let name = unsafe { CStr::from_ptr(name) };
+                return Some(name.to_string_lossy().into_owned());
+            }
+            (libc::ERANGE, _) if buf.len() < MAX_BUF_SIZE => {
+                buf.resize(buf.len() * 2, 0);
+            }
+            _ => return None,
+        }
+    }
+}
@@ -0,0 +1,160 @@
+use unicode_width::UnicodeWidthChar;
+
+// blanks between two columns of the short listing
+const COLUMN_GAP: usize = 2;
+
+/// Number of terminal cells `s` takes: escape sequences take none, East Asian
+/// wide characters two and combining or other zero-width characters none.
+pub fn display_width(s: &str) -> usize {
+    let mut width = 0;
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            skip_escape(&mut chars);
+        } else {
+            width += c.width().unwrap_or(0);
+        }
+    }
+    width
+}
+
+// Skips the rest of an escape sequence, e.g. the `[01;34m` of a colour.
+fn skip_escape(chars: &mut std::str::Chars) {
+    match chars.next() {
+        // CSI: parameters and intermediates, then one final byte
+        Some('[') => {
+            for c in chars.by_ref() {
+                if ('\x40'..='\x7e').contains(&c) {
+                    break;
+                }
+            }
+        }
+        // OSC, e.g. hyperlinks: up to BEL or ST
+        Some(']') => {
+            let mut last = '\0';
+            for c in chars.by_ref() {
+                if c == '\x07' || (last == '\x1b' && c == '\\') {
+                    break;
+                }
+                last = c;
+            }
+        }
+        _ => {}
+    }
+}
+
+/// `s` followed by blanks up to `width` cells.
+pub fn pad(s: &str, width: usize) -> String {
+    let fill = width.saturating_sub(display_width(s));
+    format!("{s}{}", " ".repeat(fill))
+}
+
+/// Column layout of a short listing, filled top to bottom then left to right,
+/// or left to right then top to bottom when `across`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Grid {
+    pub rows: usize,
+    // width of the widest name of every column
+    pub widths: Vec<usize>,
+    pub across: bool,
+}
+
+impl Grid {
+    /// Layout of names of `widths` in `cols` columns, or fewer if fewer are
+    /// needed for the same number of rows.
+    pub fn with_cols(widths: &[usize], cols: usize) -> Self {
+        let rows = widths.len().div_ceil(cols.max(1)).max(1);
+        Self {
+            rows,
+            widths: widths
+                .chunks(rows)
+                .map(|column| column.iter().copied().max().unwrap_or(0))
+                .collect(),
+            across: false,
+        }
+    }
+    /// Like [`Grid::with_cols`], filled row by row as `ls -x` does.
+    pub fn across_with_cols(widths: &[usize], cols: usize) -> Self {
+        let cols = cols.clamp(1, widths.len().max(1));
+        let mut column_widths = vec![0; cols];
+        for (i, width) in widths.iter().enumerate() {
+            column_widths[i % cols] = column_widths[i % cols].max(*width);
+        }
+        Self {
+            rows: widths.len().div_ceil(cols).max(1),
+            widths: column_widths,
+            across: true,
+        }
+    }
+    /// Layout with the most columns that fit in `line_width` cells, every
+    /// column as wide as its own widest name, like GNU ls.
+    pub fn fit(widths: &[usize], line_width: usize, across: bool) -> Self {
+        let layout = if across {
+            Self::across_with_cols
+        } else {
+            Self::with_cols
+        };
+        // every column takes at least one cell and a gap
+        let max_cols = (line_width / (1 + COLUMN_GAP)).clamp(1, widths.len().max(1));
+        (1..=max_cols)
+            .rev()
+            .map(|cols| layout(widths, cols))
+            .find(|grid| grid.line_width() <= line_width)
+            .unwrap_or_else(|| layout(widths, 1))
+    }
+    pub fn line_width(&self) -> usize {
+        self.widths.iter().sum::<usize>() + COLUMN_GAP * self.widths.len().saturating_sub(1)
+    }
+    /// Lays out `names` in the grid, without trailing blanks.
+    pub fn render(&self, names: &[String]) -> String {
+        let mut lines = Vec::with_capacity(self.rows);
+        for row in 0..self.rows {
+            let mut line = String::new();
+            for (col, width) in self.widths.iter().enumerate() {
+                let idx = if self.across {
+                    row * self.widths.len() + col
+                } else {
+                    col * self.rows + row
+                };
+                let Some(name) = names.get(idx) else {
+                    break;
+                };
+                if col > 0 {
+                    line.push_str(&" ".repeat(COLUMN_GAP));
+                }
+                line.push_str(&pad(name, *width));
+            }
+            lines.push(line.trim_end().to_string());
+        }
+        lines.join("\n")
+    }
+}
+
+/// Names separated by commas as `ls -m` prints them, a line broken before a
+/// name that would not fit in `line_width` cells or after `per_line` names.
+pub fn commas(names: &[String], line_width: usize, per_line: Option<usize>) -> String {
+    let mut out = String::new();
+    let mut position = 0;
+    let mut on_line = 0;
+    for (i, name) in names.iter().enumerate() {
+        let last = i + 1 == names.len();
+        // the name and its separator, the last one has none
+        let width = display_width(name) + if last { 0 } else { 1 };
+        let full = per_line.is_some_and(|n| on_line >= n.max(1));
+        if on_line > 0 && (full || position + 1 + width > line_width) {
+            out.push('\n');
+            position = 0;
+            on_line = 0;
+        } else if on_line > 0 {
+            out.push(' ');
+            position += 1;
+        }
+        out.push_str(name);
+        if !last {
+            out.push(',');
+        }
+        position += width;
+        on_line += 1;
+    }
+    out
+}
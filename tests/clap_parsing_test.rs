@@ -43,8 +43,7 @@ fn flag_full_in_one_test() {
     assert!(config.numeric);
     assert!(config.humanable);
     assert!(config.reverse);
-    assert!(config.sort_type.is_some());
-    assert!(matches!(config.sort_type.unwrap(), SortType::Name));
+    assert_eq!(config.sort_keys, [SortType::Name]);
     assert!(config.one_col);
     assert!(config.inode);
     assert!(config.json_mini);
@@ -79,8 +78,7 @@ fn flag_full_full_test() {
     assert!(config.numeric);
     assert!(config.humanable);
     assert!(config.reverse);
-    assert!(config.sort_type.is_some());
-    assert!(matches!(config.sort_type.unwrap(), SortType::Name));
+    assert_eq!(config.sort_keys, [SortType::Name]);
     assert!(config.one_col);
     assert!(config.inode);
     assert!(config.json_mini);
@@ -103,8 +101,7 @@ fn flag_full_short_test() {
     assert!(config.numeric);
     assert!(config.humanable);
     assert!(config.reverse);
-    assert!(config.sort_type.is_some());
-    assert!(matches!(config.sort_type.unwrap(), SortType::Name));
+    assert_eq!(config.sort_keys, [SortType::Name]);
     assert!(config.one_col);
     assert!(config.inode);
     assert!(config.json_mini);
@@ -136,13 +133,13 @@ fn flag_valuable_test() {
     assert!(!config.numeric);
     assert!(!config.humanable);
     assert!(!config.reverse);
-    assert!(config.sort_type.is_none());
+    assert!(config.sort_keys.is_empty());
     assert!(!config.one_col);
     assert!(!config.inode);
     assert!(!config.json_mini);
     assert!(!config.json_big);
 
-    assert_eq!(config.path, PATH);
+    assert_eq!(config.paths, [PATH]);
     assert_eq!(config.cols, Some(COLS));
     assert_eq!(
         config.recursive,
@@ -165,13 +162,13 @@ fn flag_recursion_unlim_test() {
     assert!(!config.numeric);
     assert!(!config.humanable);
     assert!(!config.reverse);
-    assert!(config.sort_type.is_none());
+    assert!(config.sort_keys.is_empty());
     assert!(!config.one_col);
     assert!(!config.inode);
     assert!(!config.json_mini);
     assert!(!config.json_big);
 
-    assert_eq!(config.path, PATH);
+    assert_eq!(config.paths, [PATH]);
     assert_eq!(config.cols, Some(COLS));
     assert_eq!(config.recursive, Some(command::RecursionOptions::Unlimited));
 }
@@ -192,8 +189,7 @@ fn flag_sort_type_value_name_test() {
     assert!(!config.json_mini);
     assert!(!config.json_big);
 
-    assert!(config.sort_type.is_some());
-    assert!(matches!(config.sort_type.unwrap(), SortType::Name));
+    assert_eq!(config.sort_keys, [SortType::Name]);
 }
 
 #[test]
@@ -212,8 +208,7 @@ fn flag_sort_type_flag_name_test() {
     assert!(!config.json_mini);
     assert!(!config.json_big);
 
-    assert!(config.sort_type.is_some());
-    assert!(matches!(config.sort_type.unwrap(), SortType::Name));
+    assert_eq!(config.sort_keys, [SortType::Name]);
 }
 
 #[test]
@@ -232,8 +227,7 @@ fn flag_sort_type_value_time_test() {
     assert!(!config.json_mini);
     assert!(!config.json_big);
 
-    assert!(config.sort_type.is_some());
-    assert!(matches!(config.sort_type.unwrap(), SortType::Time));
+    assert_eq!(config.sort_keys, [SortType::Time]);
 }
 
 #[test]
@@ -252,8 +246,7 @@ fn flag_sort_type_flag_time_test() {
     assert!(!config.json_mini);
     assert!(!config.json_big);
 
-    assert!(config.sort_type.is_some());
-    assert!(matches!(config.sort_type.unwrap(), SortType::Time));
+    assert_eq!(config.sort_keys, [SortType::Time]);
 }
 
 #[test]
@@ -272,8 +265,7 @@ fn flag_sort_type_value_size_test() {
     assert!(!config.json_mini);
     assert!(!config.json_big);
 
-    assert!(config.sort_type.is_some());
-    assert!(matches!(config.sort_type.unwrap(), SortType::Size));
+    assert_eq!(config.sort_keys, [SortType::Size]);
 }
 
 #[test]
@@ -292,25 +284,139 @@ fn flag_sort_type_flag_size_test() {
     assert!(!config.json_mini);
     assert!(!config.json_big);
 
-    assert!(config.sort_type.is_some());
-    assert!(matches!(config.sort_type.unwrap(), SortType::Size));
+    assert_eq!(config.sort_keys, [SortType::Size]);
 }
 
 #[test]
 fn flag_sort_type_value_wrong_test() {
     let args = ["ls-rs", "--sort", "wrong"];
+    let error = command::ls_command()
+        .try_get_matches_from(args)
+        .unwrap_err();
+
+    assert_eq!(error.kind(), clap::error::ErrorKind::InvalidValue);
+    assert_eq!(error.exit_code(), 2);
+}
+
+#[test]
+fn flag_sort_type_value_keys_test() {
+    let cases = [
+        ("version", Some(SortType::Version)),
+        ("ext", Some(SortType::Extension)),
+        ("extension", Some(SortType::Extension)),
+        ("ctime", Some(SortType::Changed)),
+        ("atime", Some(SortType::Accessed)),
+        ("birth", Some(SortType::Created)),
+        ("inode", Some(SortType::Inode)),
+        ("width", Some(SortType::Width)),
+        ("none", None),
+    ];
+    for (key, expected) in cases {
+        let matches = command::ls_command().get_matches_from(["ls-rs", "--sort", key]);
+        let config = command::Config::clap_parse(&matches);
+        assert_eq!(config.sort_keys, Vec::from_iter(expected), "--sort {key}");
+    }
+
+    let matches = command::ls_command().get_matches_from(["ls-rs", "-X"]);
+    let config = command::Config::clap_parse(&matches);
+    assert_eq!(config.sort_keys, [SortType::Extension]);
+    let matches = command::ls_command().get_matches_from(["ls-rs", "-v"]);
+    let config = command::Config::clap_parse(&matches);
+    assert_eq!(config.sort_keys, [SortType::Version]);
+}
+
+#[test]
+fn flag_version_test() {
+    let error = command::ls_command()
+        .try_get_matches_from(["ls-rs", "--version"])
+        .unwrap_err();
+
+    assert_eq!(error.kind(), clap::error::ErrorKind::DisplayVersion);
+    assert_eq!(error.exit_code(), 0);
+}
+
+#[test]
+fn flag_sort_type_many_keys_test() {
+    let args = ["ls-rs", "--sort=type,ext,name", "--group-directories-first"];
     let matches = command::ls_command().get_matches_from(args);
     let config = command::Config::clap_parse(&matches);
 
-    assert!(!config.all);
-    assert!(!config.long);
+    assert_eq!(
+        config.sort_keys,
+        [SortType::Type, SortType::Extension, SortType::Name]
+    );
+    assert!(config.group_directories_first);
+
+    let args = ["ls-rs", "--sort", "size,wrong"];
+    assert!(command::ls_command().try_get_matches_from(args).is_err());
+}
+
+#[test]
+fn flag_color_value_test() {
+    use command::ColorMode;
+
+    let cases = [
+        (vec!["ls-rs"], ColorMode::Auto),
+        (vec!["ls-rs", "--color"], ColorMode::Always),
+        (vec!["ls-rs", "--color=never"], ColorMode::Never),
+        (vec!["ls-rs", "--color=auto"], ColorMode::Auto),
+        (vec!["ls-rs", "--colour=force"], ColorMode::Always),
+    ];
+    for (args, expected) in cases {
+        let matches = command::ls_command().get_matches_from(args);
+        let config = command::Config::clap_parse(&matches);
+        assert_eq!(config.color, expected);
+    }
+
+    let res = command::ls_command().try_get_matches_from(["ls-rs", "--color=rainbow"]);
+    assert!(res.is_err());
+}
+
+#[test]
+fn color_mode_resolve_test() {
+    use command::ColorMode;
+
+    let set = |s: &str| Some(std::ffi::OsString::from(s));
+
+    assert!(ColorMode::Auto.resolve(None, None, true));
+    assert!(!ColorMode::Auto.resolve(None, None, false));
+    assert!(!ColorMode::Auto.resolve(set("1"), None, true));
+    assert!(ColorMode::Auto.resolve(set(""), None, true));
+    assert!(ColorMode::Auto.resolve(None, set("1"), false));
+    assert!(!ColorMode::Auto.resolve(None, set("0"), false));
+    assert!(!ColorMode::Auto.resolve(set("1"), set("1"), true));
+
+    assert!(ColorMode::Always.resolve(set("1"), None, false));
+    assert!(!ColorMode::Never.resolve(None, set("1"), true));
+}
+
+#[test]
+fn flag_numeric_uid_gid_test() {
+    let args = ["ls-rs", "--numeric-uid-gid"];
+    let matches = command::ls_command().get_matches_from(args);
+    let config = command::Config::clap_parse(&matches);
+
+    assert!(config.numeric_ids);
+    assert!(config.long);
     assert!(!config.numeric);
-    assert!(!config.humanable);
-    assert!(!config.reverse);
-    assert!(!config.one_col);
-    assert!(!config.inode);
-    assert!(!config.json_mini);
-    assert!(!config.json_big);
 
-    assert!(config.sort_type.is_none());
+    let args = ["ls-rs", "-l"];
+    let matches = command::ls_command().get_matches_from(args);
+    let config = command::Config::clap_parse(&matches);
+
+    assert!(!config.numeric_ids);
+}
+
+#[test]
+fn flag_layout_test() {
+    let layout = |args: &[&str]| {
+        let matches = command::ls_command().get_matches_from(args);
+        command::Config::clap_parse(&matches).layout
+    };
+
+    assert_eq!(layout(&["ls-rs"]), command::Layout::Columns);
+    assert_eq!(layout(&["ls-rs", "-x"]), command::Layout::Across);
+    assert_eq!(layout(&["ls-rs", "-m"]), command::Layout::Commas);
+    // the last of -x and -m wins
+    assert_eq!(layout(&["ls-rs", "-m", "-x"]), command::Layout::Across);
 }
@@ -0,0 +1,79 @@
+//! Helpers of the tests that run the `fls` binary.
+
+// every test binary uses a different part of this module
+#![allow(dead_code)]
+
+use std::{fs, path::Path, process::Command};
+
+use tempfile::TempDir;
+
+/// What a run of `fls` printed and how it exited.
+pub struct Output {
+    pub stdout: String,
+    pub stderr: String,
+    pub code: Option<i32>,
+}
+
+/// `fls` to run in `dir`, in an environment that does not depend on the
+/// machine running the tests: `HOME` is `dir` and there is no user config,
+/// theme, `LS_COLORS`, `NO_COLOR` or quoting style, the locale is UTF-8. Its
+/// output goes to pipes, so there are no colours unless asked for.
+pub fn command(dir: &Path) -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_ls-rs"));
+    command
+        .current_dir(dir)
+        .env("HOME", dir)
+        .env("XDG_CONFIG_HOME", dir.join("no-config"))
+        .env("LANG", "C.UTF-8");
+    for var in [
+        "LS_COLORS",
+        "NO_COLOR",
+        "CLICOLOR_FORCE",
+        "FLS_THEME",
+        "QUOTING_STYLE",
+        "COLUMNS",
+        "LC_ALL",
+        "LC_CTYPE",
+    ] {
+        command.env_remove(var);
+    }
+    command
+}
+
+/// Runs `fls` with `args` in `dir`, see [`command`].
+pub fn fls(dir: &Path, args: &[&str]) -> Output {
+    let output = command(dir).args(args).output().unwrap();
+    Output {
+        stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
+        stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+        code: output.status.code(),
+    }
+}
+
+/// Standard output of `fls` with `args` in `dir`, which has to succeed.
+pub fn fls_ok(dir: &Path, args: &[&str]) -> String {
+    let output = fls(dir, args);
+    assert_eq!(output.code, Some(0), "fls {args:?}: {}", output.stderr);
+    output.stdout
+}
+
+/// Writes `contents` to the `/` separated `path` below `root`, creating the
+/// directories on the way.
+pub fn write(root: &Path, path: &str, contents: &str) {
+    let path = root.join(path);
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, contents).unwrap();
+}
+
+/// A temporary directory holding the `/` separated `paths`: directories for
+/// those ending with `/`, empty files for the others.
+pub fn temp_dir(paths: &[&str]) -> TempDir {
+    let dir = TempDir::new().unwrap();
+    for path in paths {
+        match path.strip_suffix('/') {
+            Some(path) => fs::create_dir_all(dir.path().join(path)).unwrap(),
+            None => write(dir.path(), path, ""),
+        }
+    }
+    dir
+}
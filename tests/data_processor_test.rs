@@ -69,7 +69,7 @@ impl DpTestConfig {
 }
 
 use ls_rs::{
-    command::{self, Config},
+    command::{self, Config, SortType},
     data_op::DataProcessor,
     files::FileSystemEntry,
 };
@@ -140,7 +140,7 @@ fn dp_sort_size_test() {
     let (fse, config) = dp_config
         .setup_config_and_start_dir(vec!["ls_rs", "-S"])
         .unwrap();
-    assert!(config.sort_type.is_some());
+    assert!(!config.sort_keys.is_empty());
 
     let dp = DataProcessor::new(fse.get_dir_entries().unwrap(), config);
     assert_eq!(dp.data_len(), count_files * 2);
@@ -161,7 +161,7 @@ fn dp_sort_name_test() {
     let (fse, config) = dp_config
         .setup_config_and_start_dir(vec!["ls_rs", "-N"])
         .unwrap();
-    assert!(config.sort_type.is_some());
+    assert!(!config.sort_keys.is_empty());
 
     let dp = DataProcessor::new(fse.get_dir_entries().unwrap(), config);
     assert_eq!(dp.data_len(), count_files * 2);
@@ -172,3 +172,62 @@ fn dp_sort_name_test() {
     assert_ne!(dp, dp_sorted);
     assert!(dp_sorted.get_entries().is_sorted_by_key(|fse| fse.name()))
 }
+
+#[test]
+fn dp_sort_allocated_test() {
+    let count_files = 10;
+    let dp_config = DpTestConfig::new(count_files, 0, Some(2048));
+    let (fse, config) = dp_config
+        .setup_config_and_start_dir(vec!["ls_rs", "--sort", "allocated"])
+        .unwrap();
+    assert_eq!(config.sort_keys, [SortType::Allocated]);
+
+    let dp = DataProcessor::new(fse.get_dir_entries().unwrap(), config);
+    let dp_sorted = dp.filter().sort();
+    assert!(
+        dp_sorted
+            .get_entries()
+            .is_sorted_by_key(|fse| fse.metadata().allocated_size())
+    )
+}
+
+#[test]
+fn dp_prepare_total_test() {
+    let count_files = 4;
+    let dp_config = DpTestConfig::new(count_files, 0, Some(1));
+    let (fse, config) = dp_config
+        .setup_config_and_start_dir(vec!["ls_rs", "-l"])
+        .unwrap();
+    let entries = fse.get_dir_entries().unwrap();
+    let expected: u64 = entries
+        .iter()
+        .map(|fse| fse.metadata().allocated_size())
+        .sum();
+
+    let prepared = DataProcessor::new(entries, config).prepare();
+    assert_eq!(
+        prepared.get_total(),
+        Some(format!("total {}", expected.div_ceil(1024)).as_str())
+    );
+    assert_eq!(prepared.get_names().len(), count_files);
+}
+
+#[test]
+fn dp_prepare_allocated_column_test() {
+    let count_files = 4;
+    let dp_config = DpTestConfig::new(count_files, 0, None);
+    let (fse, config) = dp_config
+        .setup_config_and_start_dir(vec!["ls_rs", "-s", "--color=never"])
+        .unwrap();
+    assert!(config.allocated);
+
+    let prepared = DataProcessor::new(fse.get_dir_entries().unwrap(), config).prepare();
+    // empty files allocate nothing
+    assert_eq!(prepared.get_total(), Some("total 0"));
+    assert!(prepared.get_names().iter().all(|n| n.starts_with("0 file")));
+
+    let dp_config = DpTestConfig::new(count_files, 0, None);
+    let (fse, config) = dp_config.setup_config_and_start_dir(vec!["ls_rs"]).unwrap();
+    let prepared = DataProcessor::new(fse.get_dir_entries().unwrap(), config).prepare();
+    assert_eq!(prepared.get_total(), None);
+}
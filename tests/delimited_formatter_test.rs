@@ -0,0 +1,111 @@
+use std::fs;
+
+use ls_rs::{
+    command::{self, Column, Delimiter},
+    data_op::{DataProcessor, DelimitedFormatter, OutputFormatter},
+    files::FileSystemEntry,
+};
+use tempfile::TempDir;
+
+fn entries_from(dir: &TempDir) -> Vec<FileSystemEntry> {
+    let args = ["ls-rs", "-N", dir.path().to_str().unwrap()];
+    let matches = command::ls_command().get_matches_from(args);
+    let config = command::Config::clap_parse(&matches);
+
+    let fse = FileSystemEntry::new_with_config(&config).unwrap();
+    DataProcessor::new(fse.get_dir_entries().unwrap(), config)
+        .filter()
+        .sort()
+        .into_entries()
+}
+
+#[test]
+fn csv_quoting_test() {
+    let dir = TempDir::new().unwrap();
+    fs::write(dir.path().join("plain.txt"), "1234").unwrap();
+    fs::File::create(dir.path().join("with,comma \"quoted\"")).unwrap();
+    fs::File::create(dir.path().join("with\nnewline")).unwrap();
+
+    let csv = DelimitedFormatter::new(
+        entries_from(&dir),
+        vec![Column::Name, Column::Size, Column::Type],
+        false,
+        Delimiter::Csv,
+    )
+    .format();
+    println!("{csv}");
+
+    assert_eq!(
+        csv,
+        "name,size,type\n\
+         plain.txt,4,file\n\
+         \"with\nnewline\",0,file\n\
+         \"with,comma \"\"quoted\"\"\",0,file"
+    );
+}
+
+#[test]
+fn tsv_quoting_test() {
+    let dir = TempDir::new().unwrap();
+    fs::File::create(dir.path().join("a,b")).unwrap();
+    fs::File::create(dir.path().join("tab\there")).unwrap();
+
+    let tsv = DelimitedFormatter::new(
+        entries_from(&dir),
+        vec![Column::Name, Column::Type],
+        false,
+        Delimiter::Tsv,
+    )
+    .format();
+    println!("{tsv}");
+
+    assert_eq!(tsv, "name\ttype\na,b\tfile\n\"tab\there\"\tfile");
+}
+
+#[test]
+fn columns_flag_test() {
+    let args = ["ls-rs", "--csv", "--columns", "inode,target,mtime"];
+    let matches = command::ls_command().get_matches_from(args);
+    let config = command::Config::clap_parse(&matches);
+
+    assert_eq!(config.delimited, Some(Delimiter::Csv));
+    assert_eq!(
+        config.columns,
+        vec![Column::Inode, Column::Target, Column::Mtime]
+    );
+
+    let res = command::ls_command().try_get_matches_from(["ls-rs", "--columns", "owner"]);
+    assert!(res.is_err());
+}
+
+#[cfg(unix)]
+#[test]
+fn ctime_column_test() {
+    use std::{
+        fs::FileTimes,
+        os::unix::fs::MetadataExt,
+        time::{Duration, UNIX_EPOCH},
+    };
+
+    use ls_rs::time::Time;
+
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("file");
+    let file = fs::File::create(&path).unwrap();
+    // access and modification at the epoch, the status change now
+    let times = FileTimes::new()
+        .set_accessed(UNIX_EPOCH)
+        .set_modified(UNIX_EPOCH);
+    file.set_times(times).unwrap();
+
+    let md = fs::metadata(&path).unwrap();
+    let changed = UNIX_EPOCH + Duration::new(md.ctime() as u64, md.ctime_nsec() as u32);
+    let csv = DelimitedFormatter::new(
+        entries_from(&dir),
+        vec![Column::Ctime],
+        false,
+        Delimiter::Csv,
+    )
+    .format();
+    assert_eq!(csv, format!("ctime\n{}", Time::from(changed).to_iso8601()));
+}
@@ -0,0 +1,172 @@
+mod common;
+
+use std::io;
+
+use common::fls;
+use ls_rs::diagnostics::{Diagnostic, Diagnostics, Operation, Severity};
+
+#[cfg(unix)]
+#[test]
+fn diagnostic_display_test() {
+    let diagnostic = Diagnostic::new(
+        Operation::OpenDirectory,
+        "secret",
+        io::Error::from_raw_os_error(libc::EACCES),
+        Severity::Minor,
+    );
+
+    assert_eq!(diagnostic.path, "secret");
+    assert_eq!(diagnostic.message, "Permission denied");
+    assert_eq!(
+        diagnostic.to_string(),
+        "cannot open directory 'secret': Permission denied"
+    );
+}
+
+#[test]
+fn diagnostics_exit_code_test() {
+    let diagnostics = Diagnostics::default();
+    assert_eq!(diagnostics.exit_code(), 0);
+
+    let vanished = io::Error::from(io::ErrorKind::NotFound);
+    diagnostics.record(Diagnostic::new(
+        Operation::Access,
+        "gone",
+        vanished,
+        Severity::Minor,
+    ));
+    assert_eq!(diagnostics.exit_code(), 1);
+
+    let missing = io::Error::from(io::ErrorKind::NotFound);
+    diagnostics.record(Diagnostic::new(
+        Operation::Access,
+        "missing",
+        missing,
+        Severity::Serious,
+    ));
+    assert_eq!(diagnostics.exit_code(), 2);
+    assert_eq!(diagnostics.diagnostics().len(), 2);
+}
+
+// Gives a directory its permissions back when dropped, so a failing test does
+// not leave behind a directory its temporary directory cannot remove.
+#[cfg(unix)]
+struct RestorePermissions<'a>(&'a std::path::Path);
+
+#[cfg(unix)]
+impl Drop for RestorePermissions<'_> {
+    fn drop(&mut self) {
+        use std::os::unix::fs::PermissionsExt;
+
+        let _ = std::fs::set_permissions(self.0, std::fs::Permissions::from_mode(0o755));
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn unreadable_subdirectory_exit_code_test() {
+    use std::{fs, os::unix::fs::PermissionsExt};
+
+    // root reads everything, see `too_long_path_exit_code_test`
+    if unsafe { libc::geteuid() } == 0 {
+        return;
+    }
+
+    let dir = tempfile::TempDir::new().unwrap();
+    let locked = dir.path().join("locked");
+    fs::create_dir(&locked).unwrap();
+    fs::set_permissions(&locked, fs::Permissions::from_mode(0o000)).unwrap();
+    let _restore = RestorePermissions(&locked);
+
+    let output = fls(dir.path(), &["-R", "max", "."]);
+    assert_eq!(output.code, Some(1));
+    assert!(output.stderr.contains("cannot open directory"));
+
+    let output = fls(dir.path(), &["locked"]);
+    assert_eq!(output.code, Some(2));
+}
+
+// Creates 30 levels of directories with 200 character names below `dir`,
+// deeper than `PATH_MAX`, and returns the name.
+#[cfg(target_os = "linux")]
+fn too_long_path(dir: &std::path::Path) -> String {
+    let name = "d".repeat(200);
+    // created one level at a time, relative to the level before
+    let c_name = std::ffi::CString::new(name.as_str()).unwrap();
+    let c_dir = std::ffi::CString::new(dir.as_os_str().as_encoded_bytes()).unwrap();
+    unsafe {
+        let mut fd = libc::open(c_dir.as_ptr(), libc::O_DIRECTORY);
+        for _ in 0..30 {
+            assert_eq!(libc::mkdirat(fd, c_name.as_ptr(), 0o755), 0);
+            let next = libc::openat(fd, c_name.as_ptr(), libc::O_DIRECTORY);
+            libc::close(fd);
+            fd = next;
+            assert!(fd >= 0);
+        }
+        libc::close(fd);
+    }
+    name
+}
+
+// Subdirectories nested deeper than `PATH_MAX` cannot be opened by their path,
+// not even by root.
+#[cfg(target_os = "linux")]
+#[test]
+fn too_long_path_exit_code_test() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let name = too_long_path(dir.path());
+
+    let output = fls(dir.path(), &["-R", "max", "."]);
+    assert_eq!(output.code, Some(1));
+    assert!(output.stderr.contains("File name too long"));
+    // the listing goes on above the failing directory
+    assert!(output.stdout.contains(&name));
+
+    let output = fls(dir.path(), &["missing"]);
+    assert_eq!(output.code, Some(2));
+}
+
+// Problems are recorded where the caller asks, not for the whole process.
+#[cfg(target_os = "linux")]
+#[test]
+fn scoped_diagnostics_test() {
+    use ls_rs::{command, files::FileSystemEntry};
+
+    let dir = tempfile::TempDir::new().unwrap();
+    too_long_path(dir.path());
+    let path = dir.path().to_str().unwrap();
+    let matches = command::ls_command().get_matches_from(["ls-rs", "-R", "max", path]);
+    let config = command::Config::clap_parse(&matches);
+
+    let diagnostics = Diagnostics::default();
+    FileSystemEntry::new_for_path(path, &config, &diagnostics).unwrap();
+    assert_eq!(diagnostics.exit_code(), 1);
+    assert_eq!(
+        diagnostics.diagnostics()[0].operation,
+        Operation::OpenDirectory
+    );
+
+    // a walk without `-R` never reaches the failing directories
+    let matches = command::ls_command().get_matches_from(["ls-rs", path]);
+    let config = command::Config::clap_parse(&matches);
+    let other = Diagnostics::default();
+    FileSystemEntry::new_for_path(path, &config, &other).unwrap();
+    assert_eq!(other.exit_code(), 0);
+    assert_eq!(diagnostics.exit_code(), 1);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn write_error_exit_code_test() {
+    let dir = common::temp_dir(&["file"]);
+    let output = common::command(dir.path())
+        .stdout(std::fs::File::create("/dev/full").unwrap())
+        .output()
+        .unwrap();
+
+    assert_eq!(output.status.code(), Some(2));
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr),
+        "fls: write error: No space left on device\n"
+    );
+}
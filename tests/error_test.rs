@@ -0,0 +1,82 @@
+use std::io;
+
+use ls_rs::{
+    error::{ErrorKind, FsOperation, LsError},
+    files::FileSystemEntry,
+};
+use tempfile::TempDir;
+
+#[test]
+fn error_kind_from_io_test() {
+    let kind = |kind: io::ErrorKind| ErrorKind::from_io(&io::Error::from(kind));
+
+    assert_eq!(kind(io::ErrorKind::PermissionDenied), ErrorKind::Permission);
+    assert_eq!(kind(io::ErrorKind::NotFound), ErrorKind::NotFound);
+    assert_eq!(kind(io::ErrorKind::Interrupted), ErrorKind::Other);
+    assert_eq!(kind(io::ErrorKind::InvalidData), ErrorKind::Other);
+    #[cfg(unix)]
+    assert_eq!(
+        ErrorKind::from_io(&io::Error::from_raw_os_error(libc::ELOOP)),
+        ErrorKind::Loop
+    );
+}
+
+#[test]
+fn error_context_test() {
+    let dir = TempDir::new().unwrap();
+    let missing = dir.path().join("missing");
+
+    let err = FileSystemEntry::from_path(missing.to_string_lossy()).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::NotFound);
+    assert_eq!(err.operation(), Some(FsOperation::Stat));
+    assert_eq!(err.path(), Some(missing.as_path()));
+    assert_eq!(err.message(), "No such file or directory");
+    assert_eq!(
+        err.to_string(),
+        format!(
+            "cannot stat '{}': No such file or directory",
+            missing.display()
+        )
+    );
+}
+
+#[test]
+fn error_json_test() {
+    let err = LsError::io(
+        FsOperation::ReadDir,
+        "locked",
+        io::Error::from(io::ErrorKind::PermissionDenied),
+    );
+    let value: serde_json::Value = serde_json::from_str(&err.to_json()).unwrap();
+
+    assert_eq!(value["kind"], "permission");
+    assert_eq!(value["operation"], "readdir");
+    assert_eq!(value["path"], "locked");
+    assert_eq!(value["message"], err.message());
+
+    let value: serde_json::Value =
+        serde_json::from_str(&LsError::none_from("value").to_json()).unwrap();
+    assert_eq!(value["kind"], "other");
+    assert!(value["operation"].is_null());
+    assert!(value["path"].is_null());
+}
+
+#[cfg(unix)]
+#[test]
+fn error_invalid_utf8_test() {
+    use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join(OsStr::from_bytes(b"bad\xffname"));
+    std::fs::write(&path, "").unwrap();
+
+    let entry = std::fs::read_dir(dir.path())
+        .unwrap()
+        .next()
+        .unwrap()
+        .unwrap();
+    // names that are not valid unicode are listed, not reported
+    let fse = FileSystemEntry::from_dir_entry(entry).unwrap();
+    assert_eq!(fse.os_name(), path.file_name().unwrap());
+    assert_eq!(fse.name(), "bad\u{fffd}name");
+}
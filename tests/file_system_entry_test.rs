@@ -1,7 +1,7 @@
 use tempfile::{NamedTempFile, TempDir};
 
 use ls_rs::{
-    error::LsError,
+    error::{ErrorKind, FsOperation, LsError},
     files::{FileColor, FileSystemEntry, FileType},
 };
 
@@ -59,14 +59,21 @@ fn fse_from_link_path_test() {
 fn fse_from_invalid_path_test() {
     let res = FileSystemEntry::from_path("invalid_path");
     assert!(res.is_err());
-    assert!(matches!(res, Err(LsError::IOError(_))));
+    assert!(matches!(
+        res,
+        Err(LsError::Io {
+            operation: FsOperation::Stat,
+            kind: ErrorKind::NotFound,
+            ..
+        })
+    ));
 }
 
 #[test]
 fn fse_from_empty_path_test() {
     let res = FileSystemEntry::from_path("");
     assert!(res.is_err());
-    assert!(matches!(res, Err(LsError::IOError(_))));
+    assert!(matches!(res, Err(LsError::Io { .. })));
 }
 
 #[test]
@@ -92,7 +99,7 @@ fn fse_file_styled_name_test() {
     let styled = fse.get_styled_name();
     // Until extension is not supported file is displayed without styles
     assert!(styled.starts_with("\x1b"));
-    assert!(styled.contains(fse.name()));
+    assert!(styled.contains(&*fse.name()));
     assert_ne!(styled, fse.name());
 }
 
@@ -116,7 +123,7 @@ fn fse_file_executable_styled_name_test() {
 
     let styled = fse.get_styled_name();
     assert!(styled.starts_with("\x1b[32m"));
-    assert!(styled.contains(fse.name()));
+    assert!(styled.contains(&*fse.name()));
 }
 
 #[test]
@@ -135,7 +142,7 @@ fn fse_dir_styled_name_test() {
 
     let styled = fse.get_styled_name();
     assert!(styled.starts_with("\x1b[34m"));
-    assert!(styled.contains(fse.name()));
+    assert!(styled.contains(&*fse.name()));
 }
 
 #[cfg(unix)]
@@ -163,5 +170,135 @@ fn fse_link_styled_name_test() {
 
     let styled = fse.get_styled_name();
     assert!(styled.starts_with("\x1b[36m"));
-    assert!(styled.contains(fse.name()));
+    assert!(styled.contains(&*fse.name()));
+}
+
+#[cfg(unix)]
+#[test]
+fn fse_special_socket_test() {
+    use ls_rs::files::SpecialKind;
+    use std::os::unix::net::UnixListener;
+
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("sock");
+    let _listener = UnixListener::bind(&path).unwrap();
+
+    let fse = FileSystemEntry::from_path(path.to_string_lossy()).unwrap();
+    assert!(FileType::from(&fse).is_special());
+    assert!(matches!(
+        fse,
+        FileSystemEntry::Special {
+            kind: SpecialKind::Socket,
+            ..
+        }
+    ));
+    assert_eq!(fse.type_name(), "socket");
+    assert_eq!(fse.style().suffix, Some('='));
+    assert!(fse.metadata().mode_str.starts_with('s'));
+}
+
+#[cfg(unix)]
+#[test]
+fn fse_special_fifo_test() {
+    use std::{ffi::CString, fs, os::unix::fs::PermissionsExt};
+
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("pipe");
+    let c_path = CString::new(path.to_str().unwrap()).unwrap();
+    assert_eq!(unsafe { libc::mkfifo(c_path.as_ptr(), 0o644) }, 0);
+    // whatever the umask took away
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o644)).unwrap();
+
+    let fse = FileSystemEntry::from_path(path.to_string_lossy()).unwrap();
+    assert_eq!(fse.type_name(), "fifo");
+    assert_eq!(fse.style().suffix, Some('|'));
+    // the built-in colour: `LS_COLORS` and the user theme only apply once
+    // installed, which the binary does
+    assert_eq!(fse.style().color, FileColor::Yellow);
+    assert_eq!(fse.metadata().mode_str, "prw-r--r--");
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn fse_special_char_device_test() {
+    // /dev/null is 1, 3 on every Linux system
+    let fse = FileSystemEntry::from_path("/dev/null").unwrap();
+    assert_eq!(fse.type_name(), "char_device");
+    assert!(fse.style().suffix.is_none());
+    assert!(fse.metadata().mode_str.starts_with('c'));
+    assert_eq!(fse.size_string(false), "1, 3");
+}
+
+#[cfg(unix)]
+#[test]
+fn fse_broken_link_test() {
+    let dir = TempDir::new().unwrap();
+    let link = dir.path().join("dangling");
+    std::os::unix::fs::symlink("missing-target", &link).unwrap();
+
+    let fse = FileSystemEntry::from_path(link.to_string_lossy()).unwrap();
+    assert!(fse.is_broken_link());
+    assert_eq!(fse.style().color, FileColor::Red);
+    assert_eq!(fse.long_name(false), "dangling -> missing-target");
+}
+
+#[cfg(unix)]
+#[test]
+fn fse_link_long_name_test() {
+    let dir = TempDir::new().unwrap();
+    std::fs::create_dir(dir.path().join("target-dir")).unwrap();
+    let link = dir.path().join("to-dir");
+    std::os::unix::fs::symlink("target-dir", &link).unwrap();
+
+    let fse = FileSystemEntry::from_path(link.to_string_lossy()).unwrap();
+    assert!(!fse.is_broken_link());
+    assert_eq!(fse.style().color, FileColor::Aqua);
+    // the target keeps the suffix of its own type
+    assert_eq!(fse.long_name(false), "to-dir -> target-dir/");
+    assert!(
+        fse.long_name(true)
+            .ends_with(&format!("{}/", FileColor::Blue.wrap("target-dir")))
+    );
+    // only links get an arrow
+    let dir_fse =
+        FileSystemEntry::from_path(dir.path().join("target-dir").to_string_lossy()).unwrap();
+    assert_eq!(dir_fse.long_name(false), dir_fse.styled_name(false));
+}
+
+#[cfg(unix)]
+#[test]
+fn fse_dereference_test() {
+    use ls_rs::command;
+
+    let dir = TempDir::new().unwrap();
+    std::fs::write(dir.path().join("file"), "content").unwrap();
+    std::os::unix::fs::symlink("file", dir.path().join("link")).unwrap();
+    std::os::unix::fs::symlink("missing", dir.path().join("broken")).unwrap();
+
+    let path = dir.path().to_str().unwrap();
+    let entries = |args: &[&str]| {
+        let matches = command::ls_command().get_matches_from(args.iter().chain([&path]));
+        let config = command::Config::clap_parse(&matches);
+        FileSystemEntry::new_with_config(&config)
+            .unwrap()
+            .get_dir_entries()
+            .unwrap()
+    };
+    let find = |entries: &[FileSystemEntry], name: &str| {
+        entries
+            .iter()
+            .find(|fse| fse.name() == name)
+            .cloned()
+            .unwrap()
+    };
+
+    let plain = entries(&["ls-rs"]);
+    assert!(FileType::from(&find(&plain, "link")).is_link());
+
+    let followed = entries(&["ls-rs", "-L"]);
+    let link = find(&followed, "link");
+    assert!(FileType::from(&link).is_file());
+    assert_eq!(link.metadata().size, "content".len() as u64);
+    // broken links stay links
+    assert!(find(&followed, "broken").is_broken_link());
 }
@@ -0,0 +1,204 @@
+mod common;
+
+use std::{fs, path::Path, process::Command};
+
+use common::{fls_ok, write};
+use tempfile::TempDir;
+
+fn git(dir: &Path, args: &[&str]) {
+    let status = Command::new("git")
+        .current_dir(dir)
+        .env("HOME", dir)
+        .env("GIT_CONFIG_NOSYSTEM", "1")
+        .args(["-c", "user.name=fls", "-c", "user.email=fls@example.com"])
+        .args(args)
+        .output()
+        .unwrap()
+        .status;
+    // a merge with conflicts fails on purpose
+    assert!(status.success() || args[0] == "merge");
+}
+
+// `None` if git is not installed, the tests are skipped then.
+fn git_repository() -> Option<TempDir> {
+    if Command::new("git").arg("--version").output().is_err() {
+        eprintln!("git not found, skipped");
+        return None;
+    }
+    Some(TempDir::new().unwrap())
+}
+
+fn setup() -> Option<TempDir> {
+    let dir = git_repository()?;
+    let root = dir.path();
+    git(root, &["init", "-q", "-b", "main"]);
+    write(root, ".gitignore", "*.log\n");
+    for file in [
+        "clean.txt",
+        "modified.txt",
+        "staged.txt",
+        "src/lib.rs",
+        "docs/a.md",
+    ] {
+        write(root, file, "original\n");
+    }
+    git(root, &["add", "."]);
+    git(root, &["commit", "-q", "-m", "initial"]);
+
+    write(root, "modified.txt", "changed\n");
+    write(root, "staged.txt", "changed\n");
+    write(root, "src/lib.rs", "changed!\n");
+    git(root, &["add", "staged.txt"]);
+    write(root, "new.txt", "");
+    write(root, "debug.log", "");
+    write(root, "logs/old.log", "");
+    Some(dir)
+}
+
+// `name -> status code` of every row of a long listing.
+fn codes(listing: &str) -> Vec<(String, char)> {
+    listing
+        .lines()
+        .skip(1)
+        .map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let name = fields[fields.len() - 1].trim_end_matches('/');
+            let code = fields[fields.len() - 2].chars().next().unwrap();
+            (name.to_string(), code)
+        })
+        .collect()
+}
+
+#[test]
+fn git_long_column_test() {
+    let Some(dir) = setup() else {
+        return;
+    };
+    let stdout = fls_ok(dir.path(), &["--sort", "name", "--git", "-l", "-a", "."]);
+    assert_eq!(
+        codes(&stdout),
+        [
+            (".git", 'I'),
+            (".gitignore", '-'),
+            ("clean.txt", '-'),
+            ("debug.log", 'I'),
+            ("docs", '-'),
+            ("logs", 'I'),
+            ("modified.txt", 'M'),
+            ("new.txt", 'N'),
+            ("src", 'M'),
+            ("staged.txt", 'S'),
+        ]
+        .map(|(name, code)| (name.to_string(), code))
+    );
+
+    // the same statuses from within a subdirectory
+    let stdout = fls_ok(
+        &dir.path().join("src"),
+        &["--sort", "name", "--git", "-l", "."],
+    );
+    assert_eq!(codes(&stdout), [("lib.rs".to_string(), 'M')]);
+}
+
+#[test]
+fn git_json_test() {
+    let Some(dir) = setup() else {
+        return;
+    };
+    // rules of other tools than git do not ignore anything
+    write(dir.path(), ".ignore", "new.txt\n");
+    let stdout = fls_ok(dir.path(), &["--git", "-J", "."]);
+    let json: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    let status = |name: &str| {
+        json["entries"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|e| e["name"] == name)
+            .map(|e| e["git_status"].clone())
+            .unwrap()
+    };
+    assert_eq!(json["git_status"], "modified");
+    assert_eq!(status("new.txt"), "new");
+    assert_eq!(status("staged.txt"), "staged");
+    assert_eq!(status("debug.log"), "ignored");
+
+    // no field without the option, `null` outside of a repository
+    let stdout = fls_ok(dir.path(), &["-J", "clean.txt"]);
+    assert!(!stdout.contains("git_status"));
+    let outside = TempDir::new().unwrap();
+    let stdout = fls_ok(outside.path(), &["--git", "-j", "."]);
+    assert!(stdout.contains("\"git_status\": null"));
+}
+
+#[test]
+fn git_conflict_and_pack_test() {
+    let Some(dir) = setup() else {
+        return;
+    };
+    let root = dir.path();
+    git(root, &["checkout", "-q", "--", "."]);
+    git(root, &["commit", "-q", "-m", "staged"]);
+    git(root, &["checkout", "-q", "-b", "other", "HEAD~1"]);
+    write(root, "clean.txt", "other\n");
+    git(root, &["commit", "-q", "-am", "other"]);
+    git(root, &["checkout", "-q", "main"]);
+    write(root, "clean.txt", "main\n");
+    git(root, &["commit", "-q", "-am", "main"]);
+    // objects of `HEAD` are read from a pack, deltas included
+    git(root, &["gc", "-q", "--aggressive"]);
+    git(root, &["merge", "-q", "other"]);
+
+    let stdout = fls_ok(root, &["--sort", "name", "--git", "-l", "."]);
+    let codes = codes(&stdout);
+    assert!(codes.contains(&("clean.txt".to_string(), 'C')));
+    assert!(codes.contains(&("staged.txt".to_string(), '-')));
+    assert!(codes.contains(&("docs".to_string(), '-')));
+}
+
+#[test]
+fn git_sha256_test() {
+    let Some(dir) = git_repository() else {
+        return;
+    };
+    let root = dir.path();
+    git(
+        root,
+        &["init", "-q", "-b", "main", "--object-format=sha256"],
+    );
+    write(root, "file.txt", "original\n");
+    git(root, &["add", "."]);
+    git(root, &["commit", "-q", "-m", "initial"]);
+
+    // object names of SHA-256 repositories are not read, so no status
+    let stdout = fls_ok(root, &["--git", "-j", "."]);
+    let json: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    assert_eq!(json["git_status"], serde_json::Value::Null);
+    assert_eq!(json["entries"][0]["git_status"], serde_json::Value::Null);
+}
+
+#[cfg(unix)]
+#[test]
+fn git_file_mode_test() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let Some(dir) = setup() else {
+        return;
+    };
+    let root = dir.path();
+    let chmod = |mode| {
+        fs::set_permissions(root.join("clean.txt"), fs::Permissions::from_mode(mode)).unwrap();
+    };
+    let code = || {
+        let stdout = fls_ok(root, &["--git", "-l", "clean.txt"]);
+        stdout.split_whitespace().nth_back(1).unwrap().to_string()
+    };
+
+    // only the owner's bit makes a file executable for git
+    chmod(0o654);
+    assert_eq!(code(), "-");
+    chmod(0o744);
+    assert_eq!(code(), "M");
+    git(root, &["config", "core.fileMode", "false"]);
+    assert_eq!(code(), "-");
+}
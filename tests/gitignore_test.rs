@@ -0,0 +1,105 @@
+mod common;
+
+use common::{fls_ok, write};
+use ls_rs::gitignore::IgnoreFiles;
+use tempfile::TempDir;
+
+fn setup() -> TempDir {
+    let dir = common::temp_dir(&[
+        "a.log",
+        "keep.log",
+        "root.txt",
+        "secret",
+        "x.tmp",
+        "crates/root.txt",
+        "crates/a/lib.rs",
+        "crates/a/gen/g.rs",
+        "target/debug/bin",
+        "web/src/app.js",
+        "web/node_modules/pkg/index.js",
+    ]);
+    let root = dir.path();
+    write(root, ".git/info/exclude", "secret\n");
+    write(root, ".gitignore", "target/\n*.log\n!keep.log\n/root.txt\n");
+    write(root, ".ignore", "*.tmp\n");
+    write(root, "web/.gitignore", "node_modules\n");
+    write(root, "crates/a/.fdignore", "gen/\n");
+    dir
+}
+
+#[test]
+fn gitignore_walk_test() {
+    let dir = setup();
+    let stdout = fls_ok(
+        dir.path(),
+        &["--sort", "name", "-C", "1", "-R", "max", "--gitignore", "."],
+    );
+    assert_eq!(
+        stdout,
+        ".:\ncrates/\nkeep.log\nweb/\n\n\
+         ./crates:\na/\nroot.txt\n\n\
+         ./crates/a:\nlib.rs\n\n\
+         ./web:\nsrc/\n\n\
+         ./web/src:\napp.js\n"
+    );
+
+    // without the option nothing is skipped
+    let stdout = fls_ok(dir.path(), &["--sort", "name", "-C", "1", "-R", "max", "."]);
+    assert!(stdout.contains("./web/node_modules/pkg:\n"));
+    assert!(stdout.contains("\nroot.txt\n"));
+}
+
+#[test]
+fn gitignore_from_subdirectory_test() {
+    let dir = setup();
+    // anchored patterns of parent directories still apply to the right paths
+    let stdout = fls_ok(
+        &dir.path().join("crates"),
+        &["--sort", "name", "-C", "1", "-R", "max", "--gitignore", "."],
+    );
+    assert_eq!(stdout, ".:\na/\nroot.txt\n\n./a:\nlib.rs\n");
+}
+
+#[test]
+fn gitignore_hidden_test() {
+    let dir = setup();
+    let stdout = fls_ok(
+        dir.path(),
+        &["--sort", "name", "-C", "1", "-a", "--gitignore", "."],
+    );
+    assert!(stdout.contains(".gitignore\n"));
+    assert!(!stdout.contains(".git/"));
+}
+
+#[test]
+fn gitignore_rules_test() {
+    let dir = setup();
+    let root = dir.path();
+    let ignore = IgnoreFiles::for_root(root).enter(root);
+
+    assert!(ignore.is_ignored(&root.join("target"), true));
+    // `target/` only matches directories
+    assert!(!ignore.is_ignored(&root.join("target"), false));
+    assert!(ignore.is_ignored(&root.join("deep").join("b.log"), false));
+    assert!(!ignore.is_ignored(&root.join("keep.log"), false));
+    assert!(ignore.is_ignored(&root.join("root.txt"), false));
+    assert!(!ignore.is_ignored(&root.join("crates").join("root.txt"), false));
+
+    let web = ignore.enter(&root.join("web"));
+    assert!(web.is_ignored(&root.join("web").join("node_modules"), true));
+    assert!(!ignore.is_ignored(&root.join("node_modules"), true));
+}
+
+#[test]
+fn gitignore_git_only_test() {
+    let dir = setup();
+    let root = dir.path();
+    let ignore = IgnoreFiles::for_git_root(root).enter(root);
+
+    assert!(ignore.is_ignored(&root.join("a.log"), false));
+    assert!(ignore.is_ignored(&root.join("secret"), false));
+    // `.ignore` and `.fdignore` are not git's
+    assert!(!ignore.is_ignored(&root.join("x.tmp"), false));
+    let crate_a = ignore.enter(&root.join("crates").join("a"));
+    assert!(!crate_a.is_ignored(&root.join("crates").join("a").join("gen"), true));
+}
@@ -1,4 +1,4 @@
-use ls_rs::{files::FileSystemEntry, json::Serializer};
+use ls_rs::{diagnostics::Diagnostics, files::FileSystemEntry, json::Serializer};
 use tempfile::{NamedTempFile, TempDir};
 
 fn is_valid_json(json: &str) -> bool {
@@ -59,3 +59,238 @@ fn json_fse_link_test() {
     println!("{}", &json);
     assert!(is_valid_json(&json));
 }
+
+#[test]
+fn long_json_fse_file_test() {
+    let file = NamedTempFile::new().unwrap();
+
+    let fse = FileSystemEntry::from_path(file.path().to_string_lossy()).unwrap();
+
+    let json = fse.long_json();
+    println!("{}", &json);
+    let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+
+    assert_eq!(value["type"], "file");
+    assert_eq!(value["path"], file.path().display().to_string());
+    assert_eq!(value["size"], 0);
+    assert_eq!(value["human_size"], "0B");
+    assert!(value["modified_at"]["epoch"].as_u64().unwrap() > 0);
+    assert!(value["modified_at"]["iso"].as_str().unwrap().contains('T'));
+    assert_eq!(value["style"]["color"], "white");
+    assert!(value["style"]["suffix"].is_null());
+}
+
+#[test]
+fn long_json_fse_dir_test() {
+    let dir = TempDir::new().unwrap();
+    std::fs::File::create(dir.path().join("inner.txt")).unwrap();
+
+    let mut fse = FileSystemEntry::from_path(dir.path().to_string_lossy()).unwrap();
+    fse.fill_start_dir(None, &Diagnostics::default()).unwrap();
+
+    let json = fse.long_json();
+    println!("{}", &json);
+    let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+
+    assert_eq!(value["type"], "directory");
+    assert_eq!(value["style"]["suffix"], "/");
+    let entries = value["entries"].as_array().unwrap();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0]["name"], "inner.txt");
+    assert_eq!(entries[0]["extension"], "txt");
+}
+
+#[cfg(unix)]
+#[test]
+fn long_json_fse_broken_link_test() {
+    use std::os::unix::fs::symlink;
+
+    let dir = TempDir::new().unwrap();
+    let link = dir.path().join("dangling");
+    symlink(dir.path().join("missing"), &link).unwrap();
+
+    let fse = FileSystemEntry::from_path(link.display().to_string()).unwrap();
+
+    let json = fse.long_json();
+    println!("{}", &json);
+    let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+
+    assert_eq!(value["type"], "link");
+    assert_eq!(value["broken"], true);
+    assert!(value["resolved"].is_null());
+    assert_eq!(value["style"]["color"], "red");
+    assert!(value["inode"].as_u64().unwrap() > 0);
+}
+
+#[cfg(unix)]
+#[test]
+fn long_json_fse_resolved_link_test() {
+    use std::os::unix::fs::symlink;
+
+    let dir = TempDir::new().unwrap();
+    std::fs::create_dir(dir.path().join("target")).unwrap();
+    let link = dir.path().join("link");
+    symlink("target", &link).unwrap();
+
+    let fse = FileSystemEntry::from_path(link.display().to_string()).unwrap();
+    let value: serde_json::Value = serde_json::from_str(&fse.long_json()).unwrap();
+
+    assert_eq!(value["broken"], false);
+    assert_eq!(value["target"], "target");
+    assert_eq!(value["resolved"]["type"], "directory");
+    let target = dir.path().join("target").canonicalize().unwrap();
+    assert_eq!(value["resolved"]["path"], target.display().to_string());
+}
+
+#[cfg(unix)]
+#[test]
+fn long_json_fse_link_chain_test() {
+    use std::os::unix::fs::symlink;
+
+    // link -> sub/hop -> ../file, relative to the directory of each link
+    let dir = TempDir::new().unwrap();
+    std::fs::create_dir(dir.path().join("sub")).unwrap();
+    std::fs::write(dir.path().join("file"), "").unwrap();
+    symlink("../file", dir.path().join("sub").join("hop")).unwrap();
+    let link = dir.path().join("link");
+    symlink("sub/hop", &link).unwrap();
+
+    let fse = FileSystemEntry::from_path(link.display().to_string()).unwrap();
+    let value: serde_json::Value = serde_json::from_str(&fse.long_json()).unwrap();
+
+    assert_eq!(value["target"], "sub/hop");
+    assert_eq!(value["resolved"]["type"], "file");
+    let file = dir.path().join("file").canonicalize().unwrap();
+    assert_eq!(value["resolved"]["path"], file.display().to_string());
+    // the target is shown as written
+    assert_eq!(fse.long_name(false), "link -> sub/hop");
+}
+
+#[test]
+fn json_writer_escape_test() {
+    use ls_rs::json::JsonWriter;
+
+    let mut writer = JsonWriter::new(Vec::new(), false);
+    writer.begin_object().unwrap();
+    writer
+        .field_str("name", "quote\" back\\slash\nnew\ttab\u{1}ctl")
+        .unwrap();
+    writer.key("list").unwrap();
+    writer.begin_array().unwrap();
+    writer.uint(1).unwrap();
+    writer.null().unwrap();
+    writer.end_array().unwrap();
+    writer.key("empty").unwrap();
+    writer.begin_object().unwrap();
+    writer.end_object().unwrap();
+    writer.end_object().unwrap();
+
+    let json = String::from_utf8(writer.into_inner()).unwrap();
+    assert_eq!(
+        json,
+        r#"{"name":"quote\" back\\slash\nnew\ttab\u0001ctl","list":[1,null],"empty":{}}"#
+    );
+
+    let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(value["name"], "quote\" back\\slash\nnew\ttab\u{1}ctl");
+}
+
+#[test]
+fn json_fse_special_name_test() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("we\"ird\\na\tme");
+    std::fs::File::create(&path).unwrap();
+
+    let fse = FileSystemEntry::from_path(path.to_string_lossy()).unwrap();
+
+    for json in [fse.short_json(), fse.long_json()] {
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["name"], "we\"ird\\na\tme");
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn json_fse_link_type_test() {
+    use std::os::unix::fs::symlink;
+
+    let file = NamedTempFile::new().unwrap();
+    let symlink_path = file.path().with_extension("typelink");
+    symlink(file.path(), &symlink_path).unwrap();
+
+    let fse = FileSystemEntry::from_path(symlink_path.display().to_string()).unwrap();
+    let value: serde_json::Value = serde_json::from_str(&fse.short_json()).unwrap();
+    assert_eq!(value["type"], "link");
+    std::fs::remove_file(symlink_path).unwrap();
+}
+
+#[test]
+fn json_lines_recursive_test() {
+    use ls_rs::{
+        command,
+        data_op::{JsonLinesFormatter, OutputFormatter},
+    };
+
+    let dir = TempDir::new().unwrap();
+    std::fs::create_dir(dir.path().join("nested")).unwrap();
+    std::fs::File::create(dir.path().join("nested").join("deep.txt")).unwrap();
+    std::fs::File::create(dir.path().join("top.txt")).unwrap();
+
+    let args = [
+        "ls-rs",
+        "--ndjson",
+        "-R",
+        "max",
+        dir.path().to_str().unwrap(),
+    ];
+    let matches = command::ls_command().get_matches_from(args);
+    let config = command::Config::clap_parse(&matches);
+    assert!(config.json_lines);
+
+    let root = FileSystemEntry::root_with_config(&config).unwrap();
+    let diagnostics = Diagnostics::default();
+    let output = JsonLinesFormatter::new(root, &config, &diagnostics).format();
+    println!("{}", &output);
+
+    let lines = output
+        .lines()
+        .map(|l| serde_json::from_str::<serde_json::Value>(l).unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(lines.len(), 4);
+    assert_eq!(lines[0]["depth"], 0);
+    assert!(lines.iter().all(|l| l.get("entries").is_none()));
+
+    let deep = lines.iter().find(|l| l["name"] == "deep.txt").unwrap();
+    assert_eq!(deep["depth"], 2);
+    assert_eq!(
+        deep["path"],
+        dir.path()
+            .join("nested")
+            .join("deep.txt")
+            .display()
+            .to_string()
+    );
+}
+
+#[test]
+fn json_schema_version_test() {
+    use ls_rs::json::{SCHEMA, SCHEMA_VERSION};
+
+    let schema: serde_json::Value = serde_json::from_str(SCHEMA).unwrap();
+    assert_eq!(
+        schema["$defs"]["schema_version"]["const"].as_u64(),
+        Some(SCHEMA_VERSION)
+    );
+
+    let dir = TempDir::new().unwrap();
+    std::fs::File::create(dir.path().join("inner.txt")).unwrap();
+    let mut fse = FileSystemEntry::from_path(dir.path().to_string_lossy()).unwrap();
+    fse.fill_start_dir(None, &Diagnostics::default()).unwrap();
+
+    for json in [fse.short_json(), fse.long_json()] {
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["schema_version"].as_u64(), Some(SCHEMA_VERSION));
+        // only the document root carries the version
+        assert!(value["entries"][0].get("schema_version").is_none());
+    }
+}
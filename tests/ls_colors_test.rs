@@ -0,0 +1,78 @@
+mod common;
+
+use ls_rs::ls_colors::{Indicator, LsColors};
+
+const SPEC: &str = "rs=0:di=01;34:ln=01;36:or=40;31;01:ex=01;32:tw=30;42:\
+                    *.tar=01;31:*.TXT=01;33:*.txt=00;33:*README=04";
+
+#[test]
+fn ls_colors_indicators_test() {
+    let colors = LsColors::parse(SPEC);
+
+    assert_eq!(colors.indicator(Indicator::Directory), Some("01;34"));
+    assert_eq!(colors.indicator(Indicator::Link), Some("01;36"));
+    assert_eq!(colors.indicator(Indicator::Orphan), Some("40;31;01"));
+    assert_eq!(
+        colors.indicator(Indicator::StickyOtherWritable),
+        Some("30;42")
+    );
+    assert_eq!(colors.indicator(Indicator::Socket), None);
+}
+
+#[test]
+fn ls_colors_suffix_test() {
+    let colors = LsColors::parse(SPEC);
+
+    assert_eq!(colors.suffix("backup.tar"), Some("01;31"));
+    assert_eq!(colors.suffix("notes.txt"), Some("00;33"));
+    assert_eq!(colors.suffix("NOTES.TXT"), Some("01;33"));
+    assert_eq!(colors.suffix("notes.Txt"), Some("00;33"));
+    assert_eq!(colors.suffix("README"), Some("04"));
+    assert_eq!(colors.suffix("main.rs"), None);
+}
+
+#[test]
+fn ls_colors_pick_test() {
+    let colors = LsColors::parse(SPEC);
+
+    // type indicators win over suffixes
+    assert_eq!(
+        colors.pick(&[Indicator::Executable], Some("run.tar"), Indicator::File),
+        Some("01;32")
+    );
+    assert_eq!(
+        colors.pick(&[], Some("run.tar"), Indicator::File),
+        Some("01;31")
+    );
+    // unset specific indicators fall through to the base one
+    assert_eq!(
+        colors.pick(&[Indicator::Sticky], None, Indicator::Directory),
+        Some("01;34")
+    );
+    assert_eq!(colors.pick(&[], Some("main.rs"), Indicator::File), None);
+}
+
+#[test]
+fn ls_colors_malformed_test() {
+    let colors = LsColors::parse("::di:=01:ln=:*=1;2:fi=00");
+
+    assert_eq!(colors.indicator(Indicator::Directory), None);
+    assert_eq!(colors.indicator(Indicator::Link), None);
+    assert_eq!(colors.indicator(Indicator::File), Some("00"));
+    // `*=1;2` has no suffix and must not color every name
+    assert_eq!(colors.suffix("main.rs"), None);
+}
+
+#[test]
+fn ls_colors_env_test() {
+    let dir = tempfile::TempDir::new().unwrap();
+    std::fs::create_dir(dir.path().join("sub")).unwrap();
+
+    let output = common::command(dir.path())
+        .env("LS_COLORS", "di=01;35")
+        .arg("--color=always")
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.starts_with("\x1b[01;35msub"));
+}
@@ -157,3 +157,71 @@ fn metadata_symlink_test() -> io::Result<()> {
 
     Ok(())
 }
+
+#[cfg(unix)]
+#[test]
+fn metadata_owner_group_test() -> io::Result<()> {
+    let file = NamedTempFile::new()?;
+    let md = MetaData::try_from(&file.as_file().metadata()?).unwrap();
+
+    let (uid, gid) = unsafe { (libc::getuid(), libc::getgid()) };
+    assert_eq!(md.uid, uid);
+    assert_eq!(md.gid, gid);
+
+    assert_eq!(md.owner(true), uid.to_string());
+    assert_eq!(md.group(true), gid.to_string());
+    assert!(!md.owner(false).is_empty());
+    assert!(!md.group(false).is_empty());
+
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn users_name_lookup_test() {
+    use ls_rs::users;
+
+    // uid and gid 0 are root on every Unix
+    assert_eq!(users::user_name(0).as_deref(), Some("root"));
+    // cached lookups return the same answer
+    assert_eq!(users::user_name(0).as_deref(), Some("root"));
+    assert!(users::group_name(0).is_some());
+
+    // an id the passwd database has no entry for, checked beforehand
+    let unused = (40_000..u32::MAX).find(|&uid| unsafe { libc::getpwuid(uid).is_null() });
+    if let Some(uid) = unused {
+        assert_eq!(users::user_name(uid), None);
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn metadata_nlink_blocks_test() -> io::Result<()> {
+    let dir = tempfile::TempDir::new()?;
+    let path = dir.path().join("sparse");
+    let file = std::fs::File::create(&path)?;
+    // a hole, no data blocks are written
+    file.set_len(64 * 1024 * 1024)?;
+
+    let md = MetaData::try_from(&file.metadata()?).unwrap();
+    assert_eq!(md.nlink, 1);
+    assert!(md.blksize > 0);
+    assert_eq!(md.allocated_size(), md.blocks * 512);
+    assert!(md.allocated_size() < md.size);
+
+    std::fs::hard_link(&path, dir.path().join("link"))?;
+    let md = MetaData::try_from(&file.metadata()?).unwrap();
+    assert_eq!(md.nlink, 2);
+
+    Ok(())
+}
+
+#[test]
+fn allocated_string_test() {
+    use ls_rs::files::allocated_string;
+
+    assert_eq!(allocated_string(0, false), "0");
+    assert_eq!(allocated_string(512, false), "1");
+    assert_eq!(allocated_string(4096, false), "4");
+    assert_eq!(allocated_string(4096, true), "4K");
+}
@@ -0,0 +1,63 @@
+mod common;
+
+use common::fls;
+use ls_rs::command;
+use tempfile::TempDir;
+
+fn setup() -> TempDir {
+    common::temp_dir(&["alpha/inner.txt", "beta/", "file.txt"])
+}
+
+#[test]
+fn operands_parsing_test() {
+    let matches = command::ls_command().get_matches_from(["ls-rs", "src", "tests", "Cargo.toml"]);
+    let config = command::Config::clap_parse(&matches);
+    assert_eq!(config.paths, ["src", "tests", "Cargo.toml"]);
+
+    let matches = command::ls_command().get_matches_from(["ls-rs"]);
+    let config = command::Config::clap_parse(&matches);
+    assert_eq!(config.paths, ["."]);
+}
+
+#[test]
+fn operands_files_first_then_headers_test() {
+    let dir = setup();
+    let output = fls(dir.path(), &["-o", "alpha", "file.txt", "beta"]);
+
+    assert_eq!(output.stdout, "file.txt\n\nalpha:\ninner.txt\n\nbeta:\n");
+    assert!(output.stderr.is_empty());
+    assert_eq!(output.code, Some(0));
+}
+
+#[test]
+fn operands_single_dir_has_no_header_test() {
+    let dir = setup();
+    let output = fls(dir.path(), &["-o", "alpha"]);
+
+    assert_eq!(output.stdout, "inner.txt\n");
+    assert_eq!(output.code, Some(0));
+}
+
+#[test]
+fn operands_missing_is_reported_test() {
+    let dir = setup();
+    let output = fls(dir.path(), &["-o", "missing", "file.txt", "alpha"]);
+
+    // the remaining operands are still listed
+    assert_eq!(output.stdout, "file.txt\n\nalpha:\ninner.txt\n");
+    assert!(output.stderr.contains("cannot access 'missing'"));
+    assert_eq!(output.code, Some(2));
+}
+
+#[test]
+fn operands_json_array_test() {
+    let dir = setup();
+    let output = fls(dir.path(), &["-j", "file.txt", "alpha"]);
+
+    let value: serde_json::Value = serde_json::from_str(&output.stdout).unwrap();
+    let roots = value.as_array().unwrap();
+    assert_eq!(roots.len(), 2);
+    assert_eq!(roots[0]["name"], "file.txt");
+    assert_eq!(roots[1]["name"], "alpha");
+    assert_eq!(output.code, Some(0));
+}
@@ -0,0 +1,147 @@
+mod common;
+
+use std::{ffi::OsStr, fs, path::Path};
+
+use common::fls_ok;
+use ls_rs::pattern::Pattern;
+use tempfile::TempDir;
+
+fn glob(pattern: &str, name: &str) -> bool {
+    Pattern::new(pattern, false)
+        .unwrap()
+        .matches(OsStr::new(name), &Path::new("/work").join(name))
+}
+
+#[test]
+fn glob_test() {
+    assert!(glob("target", "target"));
+    // no substring matches any more
+    assert!(!glob("target,node_modules", "mod"));
+    assert!(!glob("node_modules", "mod"));
+
+    assert!(glob("*.rs", "main.rs"));
+    assert!(!glob("*.rs", "main.rs.bak"));
+    assert!(glob("file?.txt", "file1.txt"));
+    assert!(!glob("file?.txt", "file10.txt"));
+    assert!(glob("[a-c]*", "beta"));
+    assert!(!glob("[!a-c]*", "beta"));
+    assert!(glob("[]]", "]"));
+    assert!(glob("\\*", "*"));
+    assert!(!glob("\\*", "x"));
+
+    // a leading dot has to be matched explicitly
+    assert!(!glob("*", ".env"));
+    assert!(glob(".*", ".env"));
+}
+
+#[test]
+fn glob_many_stars_test() {
+    // backtracking over every star would take exponential time
+    let name = "a".repeat(40);
+    assert!(!glob("*a*a*a*a*a*a*a*a*b", &name));
+    assert!(glob("*a*a*a*a*a*a*a*a*a", &name));
+    assert!(!glob("**a**a**a**a**a**a**b", &name));
+}
+
+#[test]
+fn glob_path_test() {
+    let matches = |pattern: &str, path: &str| {
+        let path = Path::new(path);
+        Pattern::new(pattern, false)
+            .unwrap()
+            .matches(path.file_name().unwrap(), path)
+    };
+    assert!(matches("target/debug", "/work/target/debug"));
+    assert!(!matches("target/debug", "/work/target/debugger"));
+    assert!(matches("**/debug", "/work/target/debug"));
+    assert!(matches("src/**/*.rs", "/work/src/a/b/main.rs"));
+    assert!(!matches("src/*.rs", "/work/src/a/main.rs"));
+}
+
+#[test]
+fn regex_test() {
+    let regex = |pattern: &str, name: &str| {
+        Pattern::new(pattern, true)
+            .unwrap()
+            .matches(OsStr::new(name), Path::new(name))
+    };
+    assert!(regex(r"file\d+\.txt", "file12.txt"));
+    // the whole name has to match
+    assert!(!regex("file", "file12.txt"));
+    assert!(regex("a|b", "b"));
+    assert!(Pattern::new("(", true).is_err());
+}
+
+#[test]
+fn ignore_and_hide_options_test() {
+    let dir = TempDir::new().unwrap();
+    for name in ["mod", "main.rs", "notes.txt", ".env"] {
+        fs::write(dir.path().join(name), "").unwrap();
+    }
+    fs::create_dir(dir.path().join("target")).unwrap();
+    fs::create_dir(dir.path().join("node_modules")).unwrap();
+
+    let fls = |args: &[&str]| {
+        fls_ok(
+            dir.path(),
+            &[&["--sort", "name", "-C", "10"], args].concat(),
+        )
+    };
+
+    assert_eq!(
+        fls(&["-I", "target,node_modules"]),
+        "main.rs mod notes.txt\n"
+    );
+    assert_eq!(
+        fls(&["-I", "*.rs", "--ignore", "*.txt"]),
+        "mod node_modules/ target/\n"
+    );
+
+    // `--hide` gives way to `-a`, `--ignore` does not
+    assert_eq!(
+        fls(&["--hide", "*.txt", "-I", "target"]),
+        "main.rs mod node_modules/\n"
+    );
+    assert_eq!(
+        fls(&["--hide", "*.txt", "-I", "target", "-a"]),
+        ".env main.rs mod node_modules/ notes.txt\n"
+    );
+
+    assert_eq!(fls(&["--regex", "-I", "n.*"]), "main.rs mod target/\n");
+    let output = common::fls(dir.path(), &["--regex", "-I", "("]);
+    assert_eq!(output.code, Some(2));
+    // regular expressions are not split at commas
+    assert_eq!(
+        fls(&["--regex", "-I", "[a-z]{1,4}"]),
+        "main.rs node_modules/ notes.txt target/\n"
+    );
+
+    // json output leaves out the same entries
+    let stdout = fls_ok(dir.path(), &["-j", "-I", "target,node_modules,*.txt"]);
+    let json: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    let names: Vec<&str> = json["entries"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|e| e["name"].as_str().unwrap())
+        .collect();
+    assert_eq!(names.len(), 2);
+    assert!(names.contains(&"mod") && names.contains(&"main.rs"));
+}
+
+#[test]
+fn ignore_comma_name_test() {
+    let dir = TempDir::new().unwrap();
+    for name in ["a,b", "a", "b"] {
+        fs::write(dir.path().join(name), "").unwrap();
+    }
+    let fls = |args: &[&str]| {
+        fls_ok(
+            dir.path(),
+            &[&["--sort", "name", "-C", "10"], args].concat(),
+        )
+    };
+    assert_eq!(fls(&["-I", "a,b"]), "a,b\n");
+    assert_eq!(fls(&["-I", "a\\,b"]), "a b\n");
+    assert_eq!(fls(&["--regex", "-I", "a,b"]), "a b\n");
+}
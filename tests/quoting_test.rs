@@ -0,0 +1,104 @@
+mod common;
+
+use std::{ffi::OsStr, fs};
+
+use common::fls_ok;
+use ls_rs::{
+    command,
+    quoting::{Quoting, QuotingStyle},
+};
+use tempfile::TempDir;
+
+fn quote(style: QuotingStyle, name: &str) -> String {
+    Quoting::new(style, false).quote(OsStr::new(name))
+}
+
+#[test]
+fn quoting_literal_test() {
+    assert_eq!(quote(QuotingStyle::Literal, "a b"), "a b");
+    assert_eq!(quote(QuotingStyle::Literal, "a\nb"), "a\nb");
+    assert_eq!(
+        Quoting::new(QuotingStyle::Literal, true).quote(OsStr::new("a\nb")),
+        "a?b"
+    );
+}
+
+#[test]
+fn quoting_shell_test() {
+    assert_eq!(quote(QuotingStyle::Shell, "plain.txt"), "plain.txt");
+    assert_eq!(quote(QuotingStyle::Shell, "a b"), "'a b'");
+    assert_eq!(quote(QuotingStyle::Shell, "it's"), "\"it's\"");
+    assert_eq!(quote(QuotingStyle::Shell, "a\nb"), "'a?b'");
+    assert_eq!(quote(QuotingStyle::ShellEscape, "a\nb"), "'a'$'\\n''b'");
+    assert_eq!(quote(QuotingStyle::ShellEscape, "\tx"), "$'\\t''x'");
+}
+
+#[test]
+fn quoting_c_and_escape_test() {
+    assert_eq!(quote(QuotingStyle::C, "a \"b\"\n"), "\"a \\\"b\\\"\\n\"");
+    assert_eq!(quote(QuotingStyle::Escape, "a b\\c"), "a\\ b\\\\c");
+}
+
+#[test]
+fn quoting_flags_test() {
+    let parse = |args: &[&str]| {
+        let matches = command::ls_command().get_matches_from(args);
+        command::Config::clap_parse(&matches).quoting
+    };
+    assert_eq!(parse(&["ls-rs", "-b"]).style, QuotingStyle::Escape);
+    assert_eq!(
+        parse(&["ls-rs", "--quoting-style", "shell-escape"]).style,
+        QuotingStyle::ShellEscape
+    );
+    assert!(parse(&["ls-rs", "-q"]).hide_control);
+}
+
+#[cfg(unix)]
+#[test]
+fn quoting_invalid_utf8_test() {
+    use std::os::unix::ffi::OsStrExt;
+
+    let name = OsStr::from_bytes(b"bad\xffname");
+    assert_eq!(
+        Quoting::new(QuotingStyle::Escape, false).quote(name),
+        "bad\\377name"
+    );
+
+    let dir = TempDir::new().unwrap();
+    fs::write(dir.path().join(name), "").unwrap();
+    fs::write(dir.path().join("new\nline"), "").unwrap();
+
+    let stdout = fls_ok(dir.path(), &["-o", "--quoting-style=shell-escape"]);
+    assert!(stdout.contains("'bad'$'\\377''name'"));
+    assert!(stdout.contains("'new'$'\\n''line'"));
+}
+
+#[cfg(unix)]
+#[test]
+fn json_names_unquoted_test() {
+    use std::os::unix::{ffi::OsStrExt, fs::symlink};
+
+    let dir = TempDir::new().unwrap();
+    fs::write(dir.path().join("with space"), "").unwrap();
+    fs::write(dir.path().join(OsStr::from_bytes(b"bad\xffname")), "").unwrap();
+    symlink("with space", dir.path().join("link")).unwrap();
+
+    let stdout = fls_ok(dir.path(), &["-J", "--quoting-style=c", "."]);
+    let json: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    let entry = |name: &str| {
+        json["entries"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|e| e["name"] == name)
+            .unwrap()
+            .clone()
+    };
+
+    // the quoting style applies to text output only
+    let plain = entry("with space");
+    assert!(plain.get("name_bytes").is_none());
+    assert_eq!(entry("link")["target"], "with space");
+    let bad = entry("bad\u{fffd}name");
+    assert_eq!(bad["name_bytes"], serde_json::json!(b"bad\xffname"));
+}
@@ -0,0 +1,74 @@
+mod common;
+
+use common::fls_ok;
+use tempfile::TempDir;
+
+fn setup() -> TempDir {
+    common::temp_dir(&[
+        "alpha/deep/leaf",
+        "alpha/inner.txt",
+        ".hidden/",
+        "empty/",
+        "file.txt",
+    ])
+}
+
+#[test]
+fn recursive_sections_test() {
+    let dir = setup();
+    let stdout = fls_ok(
+        dir.path(),
+        &["--sort", "name", "-C", "10", "-R", "max", "."],
+    );
+    assert_eq!(
+        stdout,
+        ".:\nalpha/ empty/ file.txt\n\n\
+         ./alpha:\ndeep/ inner.txt\n\n\
+         ./alpha/deep:\nleaf\n\n\
+         ./empty:\n"
+    );
+
+    let stdout = fls_ok(
+        dir.path(),
+        &[
+            "--sort", "name", "-C", "10", "-R", "max", "-a", "alpha", ".",
+        ],
+    );
+    assert!(stdout.starts_with("alpha:\ndeep/ inner.txt\n\nalpha/deep:\nleaf\n\n.:\n"));
+    assert!(stdout.contains("\n./.hidden:\n"));
+}
+
+#[test]
+fn recursive_sections_depth_test() {
+    let dir = setup();
+    let stdout = fls_ok(dir.path(), &["--sort", "name", "-C", "10", "-R", "2", "."]);
+    assert!(stdout.contains("./alpha:\n"));
+    // `alpha/deep` lies beyond the depth and is not read
+    assert!(!stdout.contains("./alpha/deep:"));
+}
+
+#[test]
+fn recursive_sections_long_test() {
+    let dir = setup();
+    let stdout = fls_ok(
+        dir.path(),
+        &["--sort", "name", "-C", "10", "-R", "max", "-l", "alpha"],
+    );
+    let sections: Vec<&str> = stdout.split("\n\n").collect();
+    assert_eq!(sections.len(), 2);
+    assert!(sections[0].starts_with("alpha:\ntotal "));
+    assert!(sections[1].starts_with("alpha/deep:\ntotal "));
+    assert!(sections[1].trim_end().ends_with(" leaf"));
+}
+
+#[test]
+fn recursive_indent_test() {
+    let dir = setup();
+    let stdout = fls_ok(
+        dir.path(),
+        &[
+            "--sort", "name", "-C", "10", "-R", "max", "--indent", "alpha",
+        ],
+    );
+    assert!(stdout.contains("\n  deep/\n    leaf\n"));
+}
@@ -0,0 +1,173 @@
+mod common;
+
+use std::{cmp::Ordering, fs};
+
+use common::fls_ok;
+
+use ls_rs::{
+    command,
+    sort::{Order, version_cmp},
+};
+use tempfile::TempDir;
+
+#[test]
+fn version_cmp_test() {
+    assert_eq!(version_cmp("file2", "file10"), Ordering::Less);
+    assert_eq!(version_cmp("v1.10", "v1.9"), Ordering::Greater);
+    assert_eq!(version_cmp("a007", "a7"), Ordering::Equal);
+    assert_eq!(version_cmp("a", "a1"), Ordering::Less);
+    assert_eq!(version_cmp("b", "a10"), Ordering::Greater);
+    assert_eq!(version_cmp("file", "file"), Ordering::Equal);
+}
+
+#[test]
+fn order_requested_test() {
+    let requested = |args: &[&str]| {
+        let matches = command::ls_command().get_matches_from(args);
+        let config = command::Config::clap_parse(&matches);
+        Order::requested(&config).is_some()
+    };
+    // directory order, nothing to collect and sort
+    assert!(!requested(&["ls-rs"]));
+    assert!(!requested(&["ls-rs", "--sort", "none"]));
+    assert!(requested(&["ls-rs", "--sort", "size"]));
+    assert!(requested(&["ls-rs", "-r"]));
+    assert!(requested(&["ls-rs", "--group-directories-first"]));
+}
+
+#[test]
+fn sort_keys_test() {
+    let dir = TempDir::new().unwrap();
+    for (name, size) in [
+        ("file10.txt", 1),
+        ("file2.txt", 1),
+        ("b.rs", 3),
+        ("a.rs", 3),
+        ("README", 2),
+        ("wide名前", 0),
+    ] {
+        fs::write(dir.path().join(name), "x".repeat(size)).unwrap();
+    }
+
+    let stdout = fls_ok(dir.path(), &["-C", "10", "--sort", "name"]);
+    assert_eq!(stdout, "README a.rs b.rs file10.txt file2.txt wide名前\n");
+    let stdout = fls_ok(dir.path(), &["-C", "10", "--sort", "version"]);
+    assert_eq!(stdout, "README a.rs b.rs file2.txt file10.txt wide名前\n");
+    // no extension first, equal extensions by name
+    let stdout = fls_ok(dir.path(), &["-C", "10", "-X"]);
+    assert_eq!(stdout, "README wide名前 a.rs b.rs file10.txt file2.txt\n");
+    // equal sizes by name instead of directory order
+    let stdout = fls_ok(dir.path(), &["-C", "10", "--sort", "size"]);
+    assert_eq!(stdout, "wide名前 file10.txt file2.txt README a.rs b.rs\n");
+    let stdout = fls_ok(dir.path(), &["-C", "10", "--sort", "width"]);
+    assert_eq!(stdout, "a.rs b.rs README wide名前 file2.txt file10.txt\n");
+
+    let output = common::fls(dir.path(), &["--sort", "wrong"]);
+    assert_eq!(output.code, Some(2));
+}
+
+#[cfg(unix)]
+#[test]
+fn sort_inode_test() {
+    use std::os::unix::fs::MetadataExt;
+
+    let dir = TempDir::new().unwrap();
+    let mut names = vec!["c", "a", "b"];
+    for name in &names {
+        fs::write(dir.path().join(name), "").unwrap();
+    }
+    names.sort_by_key(|name| fs::metadata(dir.path().join(name)).unwrap().ino());
+
+    let stdout = fls_ok(dir.path(), &["-C", "10", "--sort", "inode"]);
+    assert_eq!(stdout, format!("{}\n", names.join(" ")));
+}
+
+fn setup_tree() -> TempDir {
+    let dir = common::temp_dir(&[
+        "b_dir/",
+        "z_dir/inner/",
+        "z_dir/a_sub/",
+        "z_dir/b.rs",
+        "z_dir/a.txt",
+    ]);
+    for (name, size) in [("a.txt", 2), ("c.rs", 1), ("d.txt", 1)] {
+        common::write(dir.path(), name, &"x".repeat(size));
+    }
+    dir
+}
+
+#[test]
+fn sort_many_keys_test() {
+    let dir = setup_tree();
+    let stdout = fls_ok(dir.path(), &["-C", "10", "--sort=type,ext,name"]);
+    assert_eq!(stdout, "b_dir/ z_dir/ c.rs a.txt d.txt\n");
+    // ties of the size broken by the extension, then by name
+    let stdout = fls_ok(dir.path(), &["-C", "10", "--sort", "size,ext"]);
+    assert_eq!(stdout, "c.rs d.txt a.txt b_dir/ z_dir/\n");
+}
+
+#[test]
+fn sort_directories_first_test() {
+    let dir = setup_tree();
+    let stdout = fls_ok(
+        dir.path(),
+        &["-C", "10", "--sort", "name", "--group-directories-first"],
+    );
+    assert_eq!(stdout, "b_dir/ z_dir/ a.txt c.rs d.txt\n");
+    // directories stay first when reversed
+    let stdout = fls_ok(
+        dir.path(),
+        &[
+            "-C",
+            "10",
+            "--sort",
+            "name",
+            "--group-directories-first",
+            "-r",
+        ],
+    );
+    assert_eq!(stdout, "z_dir/ b_dir/ d.txt c.rs a.txt\n");
+
+    // every level of recursive output
+    let args = [
+        "-C",
+        "10",
+        "--sort",
+        "name",
+        "--group-directories-first",
+        "-R",
+        "max",
+    ];
+    let stdout = fls_ok(dir.path(), &[&args[..], &["."]].concat());
+    assert!(stdout.contains("./z_dir:\na_sub/ inner/ a.txt b.rs\n"));
+    let stdout = fls_ok(dir.path(), &[&args[..], &["--indent", "z_dir"]].concat());
+    assert!(stdout.starts_with("z_dir/\n  a_sub/\n  inner/\n  a.txt\n  b.rs\n"));
+    let stdout = fls_ok(dir.path(), &[&args[..], &["--tree", "z_dir"]].concat());
+    assert!(stdout.starts_with("z_dir/\n├── a_sub/\n├── inner/\n├── a.txt\n└── b.rs\n"));
+
+    let names = |json: &serde_json::Value| -> Vec<String> {
+        json["entries"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|e| e["name"].as_str().unwrap().to_string())
+            .collect()
+    };
+    let stdout = fls_ok(dir.path(), &[&args[..], &["-j", "."]].concat());
+    let json: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    assert_eq!(names(&json), ["b_dir", "z_dir", "a.txt", "c.rs", "d.txt"]);
+    assert_eq!(
+        names(&json["entries"][1]),
+        ["a_sub", "inner", "a.txt", "b.rs"]
+    );
+
+    let stdout = fls_ok(dir.path(), &[&args[..], &["--ndjson", "z_dir"]].concat());
+    let lines: Vec<String> = stdout
+        .lines()
+        .map(|line| {
+            let json: serde_json::Value = serde_json::from_str(line).unwrap();
+            json["name"].as_str().unwrap().to_string()
+        })
+        .collect();
+    assert_eq!(lines, ["z_dir", "a_sub", "inner", "a.txt", "b.rs"]);
+}
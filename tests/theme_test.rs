@@ -0,0 +1,98 @@
+mod common;
+
+use ls_rs::theme::{Color, Style, Theme};
+
+#[test]
+fn theme_color_parse_test() {
+    assert_eq!(Color::parse("blue"), Some(Color::Basic(4)));
+    assert_eq!(Color::parse("bright_red"), Some(Color::Basic(9)));
+    assert_eq!(Color::parse("208"), Some(Color::Indexed(208)));
+    assert_eq!(Color::parse("#ff8800"), Some(Color::Rgb(255, 136, 0)));
+
+    assert_eq!(Color::parse("#ff88"), None);
+    assert_eq!(Color::parse("256"), None);
+    assert_eq!(Color::parse("purple"), None);
+}
+
+#[test]
+fn theme_style_sgr_test() {
+    let style = Style::parse("bold underline #010203 on_bright_black").unwrap();
+    assert!(style.bold && style.underline && !style.dim && !style.italic);
+    assert_eq!(style.sgr(), "1;4;38;2;1;2;3;100");
+
+    let style = Style::parse("dim italic 33 on_17").unwrap();
+    assert_eq!(style.sgr(), "2;3;38;5;33;48;5;17");
+    assert_eq!(style.paint("x"), "\x1b[2;3;38;5;33;48;5;17mx\x1b[0m");
+
+    assert!(Style::parse("bold sparkly").is_none());
+}
+
+#[test]
+fn theme_file_parse_test() {
+    let theme = Theme::parse(
+        r##"
+# comment
+directory = "bold blue"
+link=cyan
+
+[ext]
+rs = "#dea584" # trailing comment
+"tar.gz" = "red"
+
+[perm]
+read = "yellow"
+exec = "broken colour"
+"##,
+    );
+
+    assert_eq!(theme.get("directory").unwrap().sgr(), "1;34");
+    assert_eq!(theme.get("link").unwrap().sgr(), "36");
+    assert_eq!(theme.get("ext.rs").unwrap().sgr(), "38;2;222;165;132");
+    assert_eq!(theme.get("ext.tar.gz").unwrap().sgr(), "31");
+    assert_eq!(theme.get("perm.read").unwrap().sgr(), "33");
+    assert!(theme.get("perm.exec").is_none());
+    assert!(theme.get("rs").is_none());
+}
+
+#[test]
+fn theme_paint_columns_test() {
+    let theme = Theme::parse("[perm]\nread = red\nnone = dim\n[size]\nmega = green");
+
+    assert_eq!(
+        theme.paint_mode("-r--"),
+        "-\x1b[31mr\x1b[0m\x1b[2m-\x1b[0m\x1b[2m-\x1b[0m"
+    );
+    assert_eq!(theme.paint_size("2M", 2 * 1024 * 1024), "\x1b[32m2M\x1b[0m");
+    // unthemed magnitudes stay plain
+    assert_eq!(theme.paint_size("2K", 2 * 1024), "2K");
+}
+
+#[test]
+fn theme_extension_suffix_test() {
+    let theme = Theme::parse("[ext]\ngz = green\n\"tar.gz\" = red\nrc = blue");
+
+    // the longest matching suffix wins
+    assert_eq!(theme.extension("backup.tar.gz").unwrap().sgr(), "31");
+    assert_eq!(theme.extension("notes.gz").unwrap().sgr(), "32");
+    assert_eq!(theme.extension("a.b.tar.gz").unwrap().sgr(), "31");
+    assert!(theme.extension("tar.gz.bak").is_none());
+    // a leading dot is a hidden file, not an extension
+    assert!(theme.extension(".rc").is_none());
+    assert_eq!(theme.extension(".vim.rc").unwrap().sgr(), "34");
+}
+
+#[test]
+fn theme_extension_listing_test() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let theme = dir.path().join("theme.toml");
+    std::fs::write(&theme, "[ext]\ngz = green\n\"tar.gz\" = red\n").unwrap();
+    std::fs::write(dir.path().join("backup.tar.gz"), "").unwrap();
+
+    let output = common::command(dir.path())
+        .env("FLS_THEME", &theme)
+        .args(["--color=always", "backup.tar.gz"])
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.starts_with("\x1b[31m"), "{stdout:?}");
+}
@@ -0,0 +1,92 @@
+mod common;
+
+use std::fs;
+
+use common::fls_ok;
+use ls_rs::command::{self, Charset};
+use tempfile::TempDir;
+
+fn setup() -> TempDir {
+    let dir = common::temp_dir(&["alpha/inner.txt", ".hidden/", "file.txt"]);
+    common::write(dir.path(), "alpha/deep/leaf", "12345");
+    dir
+}
+
+#[test]
+fn tree_connectors_test() {
+    let dir = setup();
+    let stdout = fls_ok(dir.path(), &["--tree", "--sort", "name"]);
+    let lines: Vec<&str> = stdout.lines().skip(1).collect();
+    assert_eq!(
+        lines,
+        [
+            "├── alpha/",
+            "│   ├── deep/",
+            "│   │   └── leaf",
+            "│   └── inner.txt",
+            "└── file.txt",
+            "",
+            "2 directories, 3 files",
+        ]
+    );
+
+    let stdout = fls_ok(dir.path(), &["--tree", "--sort", "name", "--charset=ascii"]);
+    assert!(stdout.contains("|   `-- inner.txt\n`-- file.txt\n"));
+}
+
+#[test]
+fn tree_filters_test() {
+    let dir = setup();
+    let stdout = fls_ok(
+        dir.path(),
+        &["--tree", "--sort", "name", "-a", "-I", "deep"],
+    );
+    assert!(stdout.contains(".hidden/"));
+    assert!(!stdout.contains("leaf"));
+    assert!(stdout.ends_with("2 directories, 2 files\n"));
+
+    let stdout = fls_ok(dir.path(), &["--tree", "--sort", "name", "-r"]);
+    let first = stdout.lines().nth(1).unwrap();
+    assert_eq!(first, "├── file.txt");
+}
+
+#[test]
+fn tree_sizes_test() {
+    let dir = setup();
+    let stdout = fls_ok(dir.path(), &["--tree", "--tree-size"]);
+    assert!(stdout.contains("[      5]  leaf"));
+
+    // directories count their contents and their own size
+    let stdout = fls_ok(dir.path(), &["--tree", "--du"]);
+    let deep = stdout.lines().find(|l| l.ends_with("deep/")).unwrap();
+    let size: u64 = deep
+        .split(['[', ']'])
+        .nth(1)
+        .unwrap()
+        .trim()
+        .parse()
+        .unwrap();
+    let own = fs::metadata(dir.path().join("alpha").join("deep"))
+        .unwrap()
+        .len();
+    assert_eq!(size, own + 5);
+}
+
+#[test]
+fn tree_options_test() {
+    let matches = command::ls_command().get_matches_from(["ls-rs", "--tree", "--charset", "ascii"]);
+    let config = command::Config::clap_parse(&matches);
+    let tree = config.tree.unwrap();
+    assert_eq!(tree.charset, Charset::Ascii);
+    assert!(!tree.sizes);
+    assert_eq!(config.recursive, Some(command::RecursionOptions::Unlimited));
+
+    let matches = command::ls_command().get_matches_from(["ls-rs", "--tree", "--du", "-R", "2"]);
+    let config = command::Config::clap_parse(&matches);
+    let tree = config.tree.unwrap();
+    assert!(tree.sizes && tree.aggregate);
+    assert_eq!(config.recursive, Some(command::RecursionOptions::Depth(2)));
+
+    assert_eq!(Charset::parse("unicode"), Some(Charset::Unicode));
+    assert_eq!(Charset::parse("ebcdic"), None);
+}
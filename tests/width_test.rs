@@ -0,0 +1,76 @@
+use ls_rs::width::{Grid, commas, display_width, pad};
+
+#[test]
+fn display_width_test() {
+    assert_eq!(display_width("plain.txt"), 9);
+    assert_eq!(display_width("日本語"), 6);
+    assert_eq!(display_width("e\u{301}"), 1);
+    assert_eq!(display_width("\x1b[01;34mdir\x1b[0m/"), 4);
+    assert_eq!(display_width("\x1b]8;;file:///a\x07a\x1b]8;;\x07"), 1);
+}
+
+#[test]
+fn pad_test() {
+    assert_eq!(pad("中文", 6), "中文  ");
+    assert_eq!(pad("\x1b[31mab\x1b[0m", 3), "\x1b[31mab\x1b[0m ");
+    assert_eq!(pad("toolong", 3), "toolong");
+}
+
+#[test]
+fn grid_with_cols_test() {
+    let names: Vec<String> = ["a", "bbbb", "c", "dd", "e"]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+    let widths: Vec<usize> = names.iter().map(|n| display_width(n)).collect();
+
+    let grid = Grid::with_cols(&widths, 2);
+    assert_eq!(grid.rows, 3);
+    assert_eq!(grid.widths, [4, 2]);
+    assert_eq!(grid.render(&names), "a     dd\nbbbb  e\nc");
+}
+
+#[test]
+fn grid_fit_variable_widths_test() {
+    // one wide name does not make every column wide
+    let mut widths = vec![1; 20];
+    widths[0] = 30;
+    let grid = Grid::fit(&widths, 40, false);
+    assert!(grid.line_width() <= 40);
+    assert!(grid.widths.len() > 40 / (30 + 2));
+
+    let grid = Grid::fit(&[50, 50], 40, false);
+    assert_eq!(grid.widths, [50]);
+    assert_eq!(grid.rows, 2);
+}
+
+#[test]
+fn grid_across_test() {
+    let names: Vec<String> = ["a", "bbbb", "c", "dd", "e"]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+    let widths: Vec<usize> = names.iter().map(|n| display_width(n)).collect();
+
+    let grid = Grid::across_with_cols(&widths, 2);
+    assert_eq!(grid.rows, 3);
+    assert_eq!(grid.widths, [1, 4]);
+    assert_eq!(grid.render(&names), "a  bbbb\nc  dd\ne");
+
+    let grid = Grid::fit(&widths, 11, true);
+    assert_eq!(grid.render(&names), "a   bbbb  c\ndd  e");
+    let grid = Grid::fit(&widths, 10, true);
+    assert_eq!(grid.widths, [1, 4]);
+}
+
+#[test]
+fn commas_test() {
+    let names: Vec<String> = ["one", "two", "three", "日本"]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+    assert_eq!(commas(&names, 80, None), "one, two, three, 日本");
+    assert_eq!(commas(&names, 16, None), "one, two, three,\n日本");
+    assert_eq!(commas(&names, 80, Some(2)), "one, two,\nthree, 日本");
+    assert_eq!(commas(&[], 80, None), "");
+}